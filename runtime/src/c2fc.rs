@@ -1,22 +1,32 @@
 // use core::convert::AsMut;
+use rstd::prelude::*;
 use rstd::result;
 
 // use primitives::Bytes;
 // use primitives::U256;
 // use primitives::convert_hash;
-use runtime_primitives::traits::{Hash, Zero};
+use runtime_primitives::traits::{As, Hash, Zero, One, CheckedAdd};
+use runtime_primitives::Permill;
+use runtime_primitives::transaction_validity::{TransactionValidity, TransactionLongevity};
 
 use support::StorageMap;
 use support::StorageValue;
 use support::dispatch::Result;
 use support::{decl_module, decl_storage, decl_event};
 use support::{ensure, fail};
-use system::ensure_signed;
+use system::{ensure_signed, ensure_root, ensure_inherent};
 use balances::BalanceLock;
 
-use support::traits::Currency;
+use support::traits::{Currency, OnUnbalanced, ExistenceRequirement, Imbalance};
 use support::traits::{LockableCurrency, LockIdentifier, WithdrawReason, WithdrawReasons};
 
+use crate::enumerable::{EnumerableSet, EnumerableMap};
+
+mod migration;
+pub use self::migration::STORAGE_VERSION;
+mod invariants;
+pub use self::invariants::{Collection, Violation};
+
 #[cfg(feature = "std")]
 use serde::{Serialize, Deserialize};
 use parity_codec::{Encode, Decode};
@@ -55,6 +65,10 @@ pub struct Promise<Hash, Balance, AccountId, BlockNumber> {
 	filled: Balance,
 	/// time (in blocks) when current period was started
 	acception_dt: BlockNumber,
+
+	/// share of the revenue reported by oracles which is due each period,
+	/// `value` is due if the revenue isn't reported
+	revenue_share: Option<Permill>,
 }
 
 /// Describes not accepted "free promise"
@@ -68,27 +82,220 @@ pub struct FreePromise<Hash, Balance, /* Stake, */ BlockNumber> {
 	period: BlockNumber,
 	/// time of the end of promise
 	until: Option<BlockNumber>,
+	/// share of the reported revenue due each period
+	revenue_share: Option<Permill>,
+	/// only verified accounts can issue, accept and own the promise
+	verified_only: bool,
+}
+
+/// Revenue of the issuer for a period of revenue-share promise
+/// reported by an oracle.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct RevenueReport<AccountId, Balance> {
+	oracle: AccountId,
+	revenue: Balance,
+	/// disputed report is ignored until resolved by root
+	disputed: bool,
+}
+
+/// Verification of the account by root or an attestor.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct Attestation<AccountId, Hash, BlockNumber> {
+	/// `None` if attested by root
+	pub attestor: Option<AccountId>,
+	/// hash of the off-chain KYC documents
+	pub doc_hash: Hash,
+	/// the account isn't verified since this block
+	pub expires_at: BlockNumber,
+}
+
+/// Group of calls which can be paused by root.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub enum PauseCategory {
+	/// all calls of the module
+	All,
+	/// creation, change and acceptance of c2fc and promises
+	Creation,
+	/// sale and transfer of c2fc
+	Trading,
+	/// fills and debit authorizations, breaches aren't slashed while paused
+	Filling,
+	/// stakes of the promises
+	Staking,
+	/// revenue reports of the oracles
+	Reporting,
+}
+
+/// Limits of the market governed by root.
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct C2fcParameters<Balance, BlockNumber> {
+	/// min `period` of the promise
+	pub min_period: BlockNumber,
+	/// max `period` of the promise
+	pub max_period: BlockNumber,
+	/// max `value` of the promise
+	pub max_value: Balance,
+	/// max price of the c2fc for sale
+	pub max_price: Balance,
+	/// max number of open promises of the issuer, not accepted or accepted and not closed
+	pub max_promises_per_account: u64,
+	/// blocks after the end of the period while its revenue can still be reported
+	pub report_grace: BlockNumber,
+	/// protocol fee deducted from the price of each sold c2fc
+	pub sale_fee: Permill,
+	/// protocol fee paid on top of each deposit filled into c2fc
+	pub fill_fee: Permill,
+}
+
+/// Only periods shorter than one block are forbidden by default.
+impl<Balance: As<u64>, BlockNumber: As<u64>> Default for C2fcParameters<Balance, BlockNumber> {
+	fn default() -> Self {
+		C2fcParameters {
+			min_period: BlockNumber::sa(1),
+			max_period: BlockNumber::sa(u64::max_value()),
+			max_value: Balance::sa(u64::max_value()),
+			max_price: Balance::sa(u64::max_value()),
+			max_promises_per_account: 1_000,
+			report_grace: BlockNumber::sa(0),
+			sale_fee: Permill::from_millionths(0),
+			fill_fee: Permill::from_millionths(0),
+		}
+	}
+}
+
+pub type ParametersOf<T> = C2fcParameters<<T as balances::Trait>::Balance, <T as system::Trait>::BlockNumber>;
+
+
+/// Error code of invalid unsigned transaction.
+const INVALID_UNSIGNED_CALL: i8 = -1;
+/// Error code of transaction with paused call.
+pub const PAUSED_CALL: i8 = -2;
+/// Error code of transaction rejected by the checks of the signed calls.
+pub const REJECTED_CALL: i8 = -3;
+
+// Results of the `C2fcApi` runtime API:
+
+/// Bucket with its current state.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct BucketInfo<Hash, Balance, AccountId, BlockNumber> {
+	pub bucket: Bucket<Hash, Balance, AccountId, BlockNumber>,
+	pub owner: AccountId,
+	/// issuer of the accepted promise
+	pub contributor: Option<AccountId>,
+	/// arrears of the accepted promise
+	pub arrears: Balance,
+	/// funds held in escrow for the bucket
+	pub escrow: Balance,
+}
+
+/// Promise (free or accepted) with its issuer and stake.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct PromiseInfo<Hash, Balance, AccountId, BlockNumber> {
+	pub promise: FreePromise<Hash, Balance, BlockNumber>,
+	pub issuer: AccountId,
+	/// bucket of the accepted promise
+	pub c2fc_id: Option<Hash>,
+	pub stake: Balance,
+}
+
+/// Filter of free promises, `None` matches everything.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct PromiseFilter<AccountId, Balance, BlockNumber> {
+	pub issuer: Option<AccountId>,
+	pub min_value: Option<Balance>,
+	pub max_value: Option<Balance>,
+	pub max_period: Option<BlockNumber>,
+	pub revenue_share: Option<bool>,
+	pub min_stake: Option<Balance>,
+}
+
+/// Payment schedule of accepted promise.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct PromiseSchedule<Hash, Balance, BlockNumber> {
+	pub promise_id: Hash,
+	pub c2fc_id: Hash,
+	/// due for the current period
+	pub due: Balance,
+	/// not yet filled part of `due`
+	pub outstanding: Balance,
+	pub arrears: Balance,
+	/// end of the current period
+	pub next_deadline: BlockNumber,
 }
 
 
+/// Offchain worker warns about unfilled promises this number of blocks before the deadline.
+const DEADLINE_WARNING_BLOCKS: u64 = 10;
+
+/// Identifier of the escrow account owned by the module.
+const ESCROW_ID: &[u8; 8] = b"c2fc/esc";
+/// Identifier of the account holding the insurance pool.
+const INSURANCE_ID: &[u8; 8] = b"c2fc/ins";
+
+type NegativeImbalanceOf<T> = <balances::Module<T> as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
+
+/// Submits unsigned calls of the module from the offchain worker.
+pub trait SubmitUnsigned<Call> {
+	fn submit_unsigned(call: Call) -> result::Result<(), ()>;
+}
+
 pub trait Trait: system::Trait + balances::Trait {
+	type SubmitTransaction: SubmitUnsigned<Call<Self>>;
 	type Stake: LockableCurrency<Self::AccountId, Moment = <Self as system::Trait>::BlockNumber>;
+	/// Receiver of the protocol fees. The runtime routes them to the stake module,
+	/// which keeps its `FeeShare` for the rewards and passes the rest to the treasury.
+	type OnFee: OnUnbalanced<NegativeImbalanceOf<Self>>;
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 }
 
 
+// enumerable indexes:
+type AllBuckets<T> = EnumerableSet<<T as system::Trait>::Hash, AllBucketsArray<T>, AllBucketsCount<T>, AllBucketsIndex<T>>;
+type OwnedBuckets<T> = EnumerableMap<<T as system::Trait>::AccountId, <T as system::Trait>::Hash, OwnedBucketsArray<T>, OwnedBucketsCount<T>, OwnedBucketsIndex<T>>;
+type FreePromises<T> = EnumerableSet<<T as system::Trait>::Hash, FreePromisesArray<T>, FreePromisesCount<T>, FreePromisesIndex<T>>;
+type OwnedPromises<T> = EnumerableMap<<T as system::Trait>::AccountId, <T as system::Trait>::Hash, OwnedPromisesArray<T>, OwnedPromisesCount<T>, OwnedPromisesIndex<T>>;
+type AcceptedPromises<T> = EnumerableSet<<T as system::Trait>::Hash, AcceptedPromisesArray<T>, AcceptedPromisesCount<T>, AcceptedPromisesIndex<T>>;
+type IssuerPromises<T> = EnumerableMap<<T as system::Trait>::AccountId, <T as system::Trait>::Hash, IssuerPromisesArray<T>, IssuerPromisesCount<T>, IssuerPromisesIndex<T>>;
+
+
 decl_event!(
 	pub enum Event<T>
 	where
 		<T as system::Trait>::AccountId,
 		<T as system::Trait>::Hash,
+		<T as system::Trait>::BlockNumber,
 		<T as balances::Trait>::Balance,
+		InvariantViolation = invariants::ViolationOf<T>,
+		MarketParameters = ParametersOf<T>,
 	{
 		C2fcCreated(AccountId, Hash),
+		/// (owner:AccountId, c2fc_id:Hash)
 		/// OwnerSet: from, to, c2fc
 		PriceSet(AccountId, Hash, Balance),
 		Transferred(AccountId, AccountId, Hash),
 		Bought(AccountId, AccountId, Hash, Balance),
+		/// (owner:AccountId, approved:AccountId, c2fc_id:Hash)
+		Approved(AccountId, AccountId, Hash),
+		/// (owner:AccountId, c2fc_id:Hash)
+		ApprovalCleared(AccountId, Hash),
+		/// (owner:AccountId, operator:AccountId, approved:bool)
+		ApprovalForAll(AccountId, AccountId, bool),
+		/// (owner:AccountId, c2fc_id:Hash, payout:AccountId)
+		PayoutSet(AccountId, Hash, AccountId),
+		/// (owner:AccountId, c2fc_id:Hash)
+		PayoutCleared(AccountId, Hash),
+		/// (owner:AccountId, payout:AccountId)
+		DefaultPayoutSet(AccountId, AccountId),
+		/// (owner:AccountId)
+		DefaultPayoutCleared(AccountId),
 
 
 		/// FreePromise is created.
@@ -104,11 +311,85 @@ decl_event!(
 		PromiseFullilled(Hash, Hash),
 		/// (c2fc_id:Hash, promise_id:Hash, missed_deposit:Balance)
 		PromiseBreached(Hash, Hash, Balance),
+		/// Promise is ended and removed from the c2fc.
+		/// (c2fc_id:Hash, promise_id:Hash)
+		PromiseClosed(Hash, Hash),
 
 		// Staking / Locking:
 		Stake(Hash, AccountId, Balance),
 		// Stake(Hash, AccountId, StakeBalance<Self>),
 		Withdraw(Hash, AccountId, Balance),
+
+		// Protocol fees:
+		/// (payer:AccountId, c2fc_id:Hash, fee:Balance)
+		FeeCharged(AccountId, Hash, Balance),
+
+		// KYC attestations:
+		AttestorAdded(AccountId),
+		AttestorRemoved(AccountId),
+		/// (account:AccountId, doc_hash:Hash, expires_at:BlockNumber)
+		Attested(AccountId, Hash, BlockNumber),
+		AttestationRevoked(AccountId),
+
+		// Governance:
+		ParametersSet(MarketParameters),
+		Paused(PauseCategory),
+		Unpaused(PauseCategory),
+		AccountDenied(AccountId),
+		AccountAllowed(AccountId),
+
+		// Breach & insurance:
+		/// (promise_id:Hash, issuer:AccountId, slashed:Balance)
+		StakeSlashed(Hash, AccountId, Balance),
+		/// (enabled:bool, premium:Permill, contribution:Permill, coverage:Balance)
+		InsuranceSet(bool, Permill, Permill, Balance),
+		/// (promise_id:Hash, issuer:AccountId, premium:Balance)
+		InsurancePremiumPaid(Hash, AccountId, Balance),
+		/// Contribution slashed from the stake above the shortfall went to the pool.
+		/// (promise_id:Hash, amount:Balance)
+		InsurancePoolFunded(Hash, Balance),
+		/// (c2fc_id:Hash, promise_id:Hash, compensation:Balance)
+		InsuranceClaimPaid(Hash, Hash, Balance),
+
+		// Escrow:
+		/// (c2fc_id:Hash, from:AccountId, amount:Balance)
+		EscrowHeld(Hash, AccountId, Balance),
+		/// (c2fc_id:Hash, to:AccountId, amount:Balance)
+		EscrowReleased(Hash, AccountId, Balance),
+		/// (payout:AccountId, amount:Balance)
+		ProceedsClaimed(AccountId, Balance),
+
+		// Automatic debits:
+		/// (promise_id:Hash, issuer:AccountId, limit:Balance)
+		DebitAuthorized(Hash, AccountId, Balance),
+		/// (promise_id:Hash, issuer:AccountId)
+		DebitRevoked(Hash, AccountId),
+		/// (c2fc_id:Hash, promise_id:Hash, value:Balance)
+		AutoDebitSucceeded(Hash, Hash, Balance),
+		/// (c2fc_id:Hash, promise_id:Hash, wanted_deposit:Balance)
+		AutoDebitFailed(Hash, Hash, Balance),
+
+		// Revenue oracles:
+		OracleAdded(AccountId),
+		OracleRemoved(AccountId),
+		/// (promise_id:Hash, period_index:BlockNumber, revenue:Balance)
+		RevenueReported(Hash, BlockNumber, Balance),
+		/// (promise_id:Hash, period_index:BlockNumber, by:AccountId)
+		RevenueDisputed(Hash, BlockNumber, AccountId),
+		/// (promise_id:Hash, period_index:BlockNumber, revenue:Balance)
+		DisputeResolved(Hash, BlockNumber, Balance),
+
+		// Storage migration:
+		/// Migration isn't completed in the block and continues in the next one.
+		/// (version:u32, phase:u32, position:u64)
+		StorageMigrationProgress(u32, u32, u64),
+		/// (version:u32)
+		StorageMigrated(u32),
+
+		// Diagnostics:
+		InvariantViolated(InvariantViolation),
+		/// (violations:u32)
+		InvariantsChecked(u32),
 	}
 );
 
@@ -150,12 +431,158 @@ decl_storage! {
 		/// returns `c2fc_id` for specified `promise_id`
 		AcceptedPromiseBucket get(c2fc_by_promise): map T::Hash => T::Hash;
 
+		// accepted promises by issuer:
+		IssuerPromisesArray get(issuer_promise_by_index): map (T::AccountId, u64) => T::Hash;
+		IssuerPromisesCount get(issuer_promises_count): map T::AccountId => u64;
+		IssuerPromisesIndex: map T::Hash => u64;
+
+		/// Closed promises can not be accepted again.
+		ClosedPromises get(is_promise_closed): map T::Hash => bool;
+		/// Number of closed promises of the issuer, they don't count toward the limit.
+		ClosedPromisesCount get(closed_promise_count): map T::AccountId => u64;
+
 		/// Counter total of locks
 		LocksCount get(locks_count): u64;
 		/// promise_id -> LockIdentifier
 		LockForPromise get(lock_for_promise): map T::Hash => LockIdentifier;
 
+		/// Missed deposits of closed periods which are not yet paid back, by `promise_id`.
+		/// Promise without arrears is in good standing.
+		PromiseArrears get(arrears_of_promise): map T::Hash => T::Balance;
+
+		/// Limits of promises and prices, see `set_parameters`.
+		Parameters get(parameters) config(): ParametersOf<T>;
+		/// Account allowed to transfer and list the c2fc, by `c2fc_id`.
+		/// Cleared when the c2fc is transferred.
+		BucketApprovals get(approved_for_c2fc): map T::Hash => Option<T::AccountId>;
+		/// (owner, operator) -> whether the operator manages all c2fc of the owner
+		OperatorApprovals get(is_approved_for_all): map (T::AccountId, T::AccountId) => bool;
+		/// Account receiving the funds paid to the owner of the c2fc, by `c2fc_id`.
+		/// Cleared when the c2fc is transferred, see `payout_account`.
+		BucketPayout get(payout_of_c2fc): map T::Hash => Option<T::AccountId>;
+		/// Account receiving the funds paid to the owner for all c2fc without own payout.
+		AccountPayout get(default_payout_of): map T::AccountId => Option<T::AccountId>;
+
+		/// Accounts which can't use the module, see `ensure_not_denied`.
+		DenyList get(is_denied): map T::AccountId => bool;
+		/// Paused groups of calls, see `pause`.
+		PausedCategories get(is_paused): map PauseCategory => bool;
+
+		/// Issuers pay premiums and breaches are covered only while the insurance is enabled.
+		InsuranceEnabled get(insurance_enabled): bool;
+		/// Premium paid by the issuer into the pool on acceptance, part of the promise value.
+		InsurancePremium get(insurance_premium): Permill;
+		/// Part of the shortfall slashed from the stake into the pool on top of the compensation.
+		InsuranceContribution get(insurance_contribution): Permill;
+		/// Max compensation paid from the pool for a single breach.
+		InsuranceCoverage get(insurance_coverage): T::Balance;
+		/// Funds held by the insurance pool.
+		/// Should be equal to the balance of the `insurance_account`.
+		InsurancePool get(insurance_pool): T::Balance;
+		/// Premium paid for the promise, by `promise_id`.
+		InsurancePremiums get(premium_of_promise): map T::Hash => T::Balance;
+		/// Total compensation paid from the pool to the c2fc, by `c2fc_id`.
+		InsuranceClaims get(claims_of_c2fc): map T::Hash => T::Balance;
+
+		/// Funds held by the escrow account for the c2fc, by `c2fc_id`.
+		BucketEscrow get(escrow_of_c2fc): map T::Hash => T::Balance;
+		/// Funds held by the escrow account for the promise, by `promise_id`.
+		PromiseEscrow get(escrow_of_promise): map T::Hash => T::Balance;
+		/// Sale proceeds held by the escrow account until claimed, by the payout account of the seller.
+		SaleProceeds get(proceeds_of): map T::AccountId => T::Balance;
+		/// Number of disputed revenue reports of the promise, by `promise_id`.
+		/// Escrow of the c2fc isn't released while any of them is open.
+		OpenDisputes get(open_disputes): map T::Hash => u32;
+		/// Sum of all funds held by the escrow account.
+		/// Should be equal to the balance of the `escrow_account`.
+		EscrowTotal get(escrow_total): T::Balance;
+
+		/// Standing authorisation of the issuer to debit the due amount at the end of each period,
+		/// by `promise_id`. Value is the max amount of a single debit.
+		DebitAuthorizations get(debit_authorization): map T::Hash => Option<T::Balance>;
+
+		/// Accounts allowed to report the revenue of issuers.
+		Oracles get(is_oracle): map T::AccountId => bool;
+		/// (promise_id, period_index) -> report
+		RevenueReports get(revenue_report): map (T::Hash, T::BlockNumber) => Option<RevenueReport<T::AccountId, T::Balance>>;
+
+		/// Accounts allowed to verify other accounts.
+		Attestors get(is_attestor): map T::AccountId => bool;
+		Attestations get(attestation_of): map T::AccountId => Option<Attestation<T::AccountId, T::Hash, T::BlockNumber>>;
+
+		/// Number of closed periods of accepted promise, by `promise_id`.
+		ClosedPeriods get(closed_periods): map T::Hash => T::BlockNumber;
+		/// Number of the last finalized block, used to validate unsigned transactions.
+		LastFinalized get(last_finalized): T::BlockNumber;
+
 		Nonce: u64;
+
+		/// Version of the storage layout, see `migration`.
+		/// New chains start with the current version.
+		StorageVersion get(storage_version) build(|_: &GenesisConfig<T>| STORAGE_VERSION): u32;
+		/// (phase, position) of the next entry to migrate.
+		MigrationCursor get(migration_cursor): (u32, u64);
+	}
+	add_extra_genesis {
+		config(oracles): Vec<T::AccountId>;
+		config(attestors): Vec<T::AccountId>;
+		/// (owner, c2fc_id, price)
+		config(buckets): Vec<(T::AccountId, T::Hash, T::Balance)>;
+		/// (issuer, promise_id, value, period, until), zero `until` means no end
+		config(promises): Vec<(T::AccountId, T::Hash, T::Balance, T::BlockNumber, T::BlockNumber)>;
+		/// (promise_id, c2fc_id) of the promises accepted at the genesis
+		config(accepted_promises): Vec<(T::Hash, T::Hash)>;
+		/// (promise_id, amount) locked by the issuer of the promise
+		config(stakes): Vec<(T::Hash, T::Balance)>;
+
+		build(|storage: &mut runtime_primitives::StorageOverlay, _: &mut runtime_primitives::ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+			runtime_io::with_storage(storage, || {
+				// the module accounts are kept alive by the endowment, which is never released:
+				let endowment = <balances::Module<T> as Currency<T::AccountId>>::minimum_balance();
+				let _ = <balances::Module<T> as Currency<T::AccountId>>::deposit_creating(&<Module<T>>::escrow_account(), endowment);
+				let _ = <balances::Module<T> as Currency<T::AccountId>>::deposit_creating(&<Module<T>>::insurance_account(), endowment);
+
+				for oracle in &config.oracles {
+					<Oracles<T>>::insert(oracle, true);
+				}
+				for attestor in &config.attestors {
+					<Attestors<T>>::insert(attestor, true);
+				}
+
+				for (owner, c2fc_id, price) in &config.buckets {
+					let bucket = Bucket {
+						id: *c2fc_id,
+						promise: None,
+						price: *price,
+					};
+					<Module<T>>::mint_c2fc(owner.clone(), *c2fc_id, bucket)
+						.expect("Buckets of the genesis should be unique");
+				}
+
+				for (issuer, promise_id, value, period, until) in &config.promises {
+					let promise = FreePromise {
+						id: *promise_id,
+						value: *value,
+						period: *period,
+						until: if !until.is_zero() { Some(*until) } else { None },
+						revenue_share: None,
+						verified_only: false,
+					};
+					<Module<T>>::mint_promise(issuer.clone(), *promise_id, promise)
+						.expect("Promises of the genesis should be unique");
+				}
+
+				for (promise_id, c2fc_id) in &config.accepted_promises {
+					<Module<T>>::attach_promise(*promise_id, *c2fc_id, Zero::zero())
+						.expect("Accepted promises of the genesis should refer to the free promises and empty buckets of other accounts");
+				}
+
+				for (promise_id, amount) in &config.stakes {
+					<Module<T>>::lock_stake(*promise_id, *amount)
+						.expect("Stakes of the genesis should refer to the promises without stake");
+				}
+			});
+		});
 	}
 }
 
@@ -165,7 +592,7 @@ decl_module! {
 		fn deposit_event<T>() = default;
 
 		fn create_c2fc(origin) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::create_c2fc())?;
 			let nonce = <Nonce<T>>::get();
 			let c2fc_id = (<system::Module<T>>::random_seed(), &sender, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
 
@@ -182,114 +609,86 @@ decl_module! {
 			Ok(())
 		}
 
-		fn create_promise_until(origin, value: T::Balance, period: T::BlockNumber, until: T::BlockNumber) -> Result {
-			let sender = ensure_signed(origin)?;
-			let nonce = <Nonce<T>>::get();
-			let promise_id = (<system::Module<T>>::random_seed(), &sender, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
-
-			let new_promise = FreePromise {
-				id: promise_id,
-				value,
-				period,
-				until: if !until.is_zero() { Some(until) } else { None },
-			};
-
-			Self::mint_promise(sender, promise_id, new_promise)?;
-
-			<Nonce<T>>::mutate(|n| *n += 1);
+		/// Create promise, the verified-only promise can be issued, accepted and owned by verified accounts only.
+		fn create_promise_until(origin, value: T::Balance, period: T::BlockNumber, until: T::BlockNumber, verified_only: bool) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::create_promise_until(value, period, until, verified_only))?;
+			Self::create_free_promise(sender, value, period, until, None, verified_only)
+		}
 
-			Ok(())
+		/// Create promise to pay `share` of the revenue reported by oracles each period.
+		/// `value` is due for periods without reported revenue.
+		fn create_revenue_share_promise(origin, share: Permill, value: T::Balance, period: T::BlockNumber, until: T::BlockNumber) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::create_revenue_share_promise(share, value, period, until))?;
+			ensure!(share != Permill::from_millionths(0), "Revenue share should be greater than zero");
+			Self::create_free_promise(sender, value, period, until, Some(share), false)
 		}
 
 		fn create_promise(origin, value: T::Balance, period: T::BlockNumber) -> Result {
-			Self::create_promise_until(origin, value, period, Zero::zero())
+			Self::create_promise_until(origin, value, period, Zero::zero(), false)
 		}
 
 
 		// TODO: fn stake_to_promise(origin, promise_id: T::Hash, amount: StakeBalance<T>) -> Result {
 		fn stake_to_promise(origin, promise_id: T::Hash, amount: T::Balance) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::stake_to_promise(promise_id, amount))?;
 
 			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
 			let owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
 			ensure!(owner == sender, "You do not own this promise");
 
-			// get data from existing promise:
-			let until = if <AcceptedPromiseBucket<T>>::exists(promise_id) {
-				let promise = {
-					let c2fc_id = <AcceptedPromiseBucket<T>>::get(promise_id);
-					let c2fc = Self::c2fc(c2fc_id);
-					let promise = c2fc.promise;
-					ensure!(promise.is_some(), "Bucket doesnt contains promise");
-					promise.unwrap()
-				};
-				promise.until
-			} else {
-				let promise = Self::promise(promise_id);
-				promise.until
-			}.unwrap_or( unsafe {
-				// end of the universe:
-				// TODO: use (crate::)BlockNumber::max_value()
-				// <T as system::Trait>::BlockNumber::from(crate::BlockNumber::max_value())
-				// <T::BlockNumber as As<crate::BlockNumber>>::sa(max as crate::BlockNumber);
-				// XXX:
-				let max = crate::BlockNumber::max_value();
-				(*(max as *const crate::BlockNumber as *const <T as system::Trait>::BlockNumber)).clone()
-			});
+			if !<LockForPromise<T>>::exists(promise_id) {
+				return Self::lock_stake(promise_id, amount);
+			}
 
+			let lock_id = Self::lock_for_promise(promise_id);
+			// select lock with specified ID:
+			let lock = get_lock::<T>(&sender, &lock_id).ok_or("Lock not found")?;
+			let new_amount = lock.amount.checked_add(&amount).ok_or("Overflow adding to the stake")?;
 
-			let reasons = WithdrawReasons::from(WithdrawReason::Reserve);
+			<balances::Module<T>>::extend_lock(lock_id, &sender, new_amount, lock.until, lock.reasons);
 
-			if <LockForPromise<T>>::exists(promise_id) {
-				let lock_id = Self::lock_for_promise(promise_id);
-				// select lock with specified ID:
-				let lock = get_lock::<T>(&sender, &lock_id);
-				let lock = { // XXX: test & remove me
-					let locks_all = <balances::Module<T>>::locks(&sender);
-					let mut locks = locks_all.into_iter().filter_map(|l|
-						if l.id == lock_id {
-							Some(l)
-						} else {
-							None
-						});
-					let lock = locks.next();
-					ensure!(lock.is_none(), "Lock not found");
-					ensure!(locks.next().is_some(), "Incorrect length of locks with same ID. WTF?!");
-					lock.unwrap()
-				};
+			Self::deposit_event(RawEvent::Stake(promise_id, sender, amount));
 
-				// TODO: check overflow:
-				// ensure!(T::Balance::max_value() - lock.amount >= amount, "Overflow max size of Balance!");
-				// e.g. crate::BlockNumber::max_value() - <T::Balance as As<crate::Balance>>::sa(lock.amount as crate::Balance) >= <T::Balance as As<crate::Balance>>::sa(amount as crate::Balance)
+			Ok(())
+		}
 
-				<balances::Module<T>>::extend_lock(lock_id, &sender, lock.amount + amount, until, reasons);
-				// <T::Stake>::extend_lock(lock_id, &sender, lock.amount + amount, until, reasons);
-			} else {
-				let lock_id = Self::next_free_lock_identifier(&promise_id);
+		/// Allow the module to pull the due amount (up to `limit`) from the free balance
+		/// of the issuer at the end of each period of the promise.
+		fn authorize_debit(origin, promise_id: T::Hash, limit: T::Balance) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::authorize_debit(promise_id, limit))?;
 
-				// <T::Stake>::set_lock(lock_id, &sender, amount, until, reasons);
-				<balances::Module<T>>::set_lock(lock_id, &sender, amount, until, reasons);
+			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
+			let owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
+			ensure!(owner == sender, "You do not own this promise");
+			ensure!(!limit.is_zero(), "Debit limit should be greater than zero");
 
-				// TODO: use T::Stake instead T::Balance:
-				// <T::Stake>::set_lock(lock_id, &sender, amount, until, reasons);
-				// <balances::Module<T::Stake>>::set_lock(lock_id, &sender, amount, until, reasons);
+			<DebitAuthorizations<T>>::insert(promise_id, limit);
 
-				// register new lock:
-				<LockForPromise<T>>::insert(promise_id, lock_id);
-				<LocksCount<T>>::mutate(|n| *n += 1);
-			}
+			Self::deposit_event(RawEvent::DebitAuthorized(promise_id, sender, limit));
 
-			Self::deposit_event(RawEvent::Stake(promise_id, sender, amount));
+			Ok(())
+		}
+
+		fn revoke_debit(origin, promise_id: T::Hash) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::revoke_debit(promise_id))?;
+
+			let owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
+			ensure!(owner == sender, "You do not own this promise");
+			ensure!(<DebitAuthorizations<T>>::exists(promise_id), "This promise has no debit authorization");
+
+			<DebitAuthorizations<T>>::remove(promise_id);
+
+			Self::deposit_event(RawEvent::DebitRevoked(promise_id, sender));
 
 			Ok(())
 		}
 
 		fn withdraw_staken(origin, promise_id: T::Hash) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::withdraw_staken(promise_id))?;
 
 			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
 
-			let owner = Self::owner_of_c2fc(promise_id).ok_or("No owner for this promise")?;
+			let owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
 			ensure!(owner == sender, "You do not own this promise");
 
 			if <LockForPromise<T>>::exists(promise_id) {
@@ -308,6 +707,7 @@ decl_module! {
 				}.unwrap_or(Zero::zero());
 
 				<balances::Module<T>>::remove_lock(lock_id, &sender);
+				<LockForPromise<T>>::remove(promise_id);
 
 				Self::deposit_event(RawEvent::Withdraw(promise_id, sender, free));
 			}
@@ -317,12 +717,14 @@ decl_module! {
 
 
 		fn edit_promise(origin, promise_id: T::Hash, value: T::Balance, period: T::BlockNumber) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::edit_promise(promise_id, value, period))?;
 
 			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
 
-			let owner = Self::owner_of_c2fc(promise_id).ok_or("No owner for this promise")?;
+			let owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
 			ensure!(owner == sender, "You do not own this promise");
+			ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), "This promise already accepted so it cannot be changed");
+			Self::ensure_promise_terms(value, period)?;
 
 			<Promises<T>>::mutate(promise_id, |promise|{
 				promise.value = value;
@@ -338,55 +740,46 @@ decl_module! {
 		/// Accept specified free promise and add it to specified c2fc.
 		/// Only owner of the c2fc can do it.
 		fn accept_promise(origin, promise_id: T::Hash, c2fc_id: T::Hash) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::accept_promise(promise_id, c2fc_id))?;
 
 			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
 			ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
 			ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), "This promise is already accepted");
-
+			ensure!(!Self::is_promise_closed(promise_id), "This promise is closed");
 
 			let c2fc_owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
-			ensure!(c2fc_owner == sender, "You do not own this promise");
+			ensure!(c2fc_owner == sender, "You do not own this c2fc");
 
 			let promise_owner = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
 			ensure!(promise_owner != sender, "You can not accept your own promise");
 
-			let mut c2fc = Self::c2fc(c2fc_id);
-			ensure!(c2fc.promise.is_none(), "Bucket already contains another promise");
+			ensure!(Self::c2fc(c2fc_id).promise.is_none(), "Bucket already contains another promise");
+			if Self::promise(promise_id).verified_only {
+				ensure!(Self::is_verified(&sender), "Only verified accounts can accept this promise");
+				ensure!(Self::is_verified(&promise_owner), "The issuer of this promise is not verified");
+			}
 
 			// get current (latest) block:
 			let current_block = <system::Module<T>>::block_number();
 
 			let free_promise = Self::promise(promise_id);
-			let promise = Promise {
-				id: free_promise.id,
-				// in the near future `owner` can be removed
-				owner: promise_owner.clone(),
-				value: free_promise.value,
-				period: free_promise.period,
-				until: free_promise.until,
-				acception_dt: current_block,
-				filled: T::Balance::zero(),
-			};
-
-			c2fc.promise = Some(promise);
-			<Buckets<T>>::insert(c2fc_id, c2fc);
-			<AcceptedPromiseBucket<T>>::insert(promise_id, c2fc_id);
-
-			// incrmnt the counter & push to maps:
-			{
-				let accepted_promises_count = Self::accepted_promises_count();
-				let new_accepted_promises_count = accepted_promises_count
-					.checked_add(1)
-					.ok_or("Overflow adding a new promise to total supply")?;
-
-				<BucketContributor<T>>::insert(c2fc_id, promise_owner);
-
-				<AcceptedPromisesArray<T>>::insert(accepted_promises_count, promise_id);
-				<AcceptedPromisesCount<T>>::put(new_accepted_promises_count);
-				<AcceptedPromisesIndex<T>>::insert(promise_id, accepted_promises_count);
+			// the parameters could be changed since the promise was created:
+			Self::ensure_promise_terms(free_promise.value, free_promise.period)?;
+			// the premium is paid before the promise is attached, which isn't rolled back:
+			Self::ensure_can_attach(promise_id, c2fc_id)?;
+
+			if Self::insurance_enabled() {
+				let premium = Self::insurance_premium() * free_promise.value;
+				if !premium.is_zero() {
+					Self::transfer_money(&promise_owner, &Self::insurance_account(), premium)?;
+					<InsurancePool<T>>::mutate(|pool| *pool += premium);
+					<InsurancePremiums<T>>::insert(promise_id, premium);
+					Self::deposit_event(RawEvent::InsurancePremiumPaid(promise_id, promise_owner.clone(), premium));
+				}
 			}
 
+			Self::attach_promise(promise_id, c2fc_id, current_block)?;
+
 			<Nonce<T>>::mutate(|n| *n += 1);
 
 			Self::deposit_event(RawEvent::PromiseAccepted(promise_id, c2fc_id));
@@ -398,36 +791,134 @@ decl_module! {
 		// selling & trasfering a c2fc //
 
 		fn set_price(origin, c2fc_id: T::Hash, new_price: T::Balance) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::set_price(c2fc_id, new_price))?;
 
 			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
 
 			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
-			ensure!(owner == sender, "You do not own this c2fc");
+			ensure!(Self::can_manage_c2fc(&sender, &owner, c2fc_id), "You do not own this c2fc");
+			ensure!(new_price <= Self::parameters().max_price, "Price exceeds the max price");
 
 			let mut c2fc = Self::c2fc(c2fc_id);
 			c2fc.price = new_price;
 
 			<Buckets<T>>::insert(c2fc_id, c2fc);
 
-			Self::deposit_event(RawEvent::PriceSet(sender, c2fc_id, new_price));
+			Self::deposit_event(RawEvent::PriceSet(owner, c2fc_id, new_price));
 
 			Ok(())
 		}
 
 		fn transfer(origin, to: T::AccountId, c2fc_id: T::Hash) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::transfer(to.clone(), c2fc_id))?;
+
+			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+			ensure!(Self::can_manage_c2fc(&sender, &owner, c2fc_id), "You do not own this c2fc");
+
+			Self::transfer_from(owner, to, c2fc_id)?;
+
+			Ok(())
+		}
+
+		/// Allow `approved` to transfer and list the c2fc until it is transferred.
+		/// Called by the owner or an operator of the owner, the previous approval is replaced.
+		fn approve(origin, approved: T::AccountId, c2fc_id: T::Hash) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::approve(approved.clone(), c2fc_id))?;
+
+			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+			ensure!(sender == owner || Self::is_approved_for_all((owner.clone(), sender)), "You do not own this c2fc");
+			ensure!(approved != owner, "You can't approve the owner of the c2fc");
+
+			<BucketApprovals<T>>::insert(c2fc_id, &approved);
+
+			Self::deposit_event(RawEvent::Approved(owner, approved, c2fc_id));
+
+			Ok(())
+		}
+
+		fn clear_approval(origin, c2fc_id: T::Hash) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::clear_approval(c2fc_id))?;
+
+			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+			ensure!(Self::can_manage_c2fc(&sender, &owner, c2fc_id), "You do not own this c2fc");
+			ensure!(<BucketApprovals<T>>::exists(c2fc_id), "This c2fc has no approval");
+
+			<BucketApprovals<T>>::remove(c2fc_id);
+
+			Self::deposit_event(RawEvent::ApprovalCleared(owner, c2fc_id));
+
+			Ok(())
+		}
+
+		/// Allow or disallow `operator` to manage all c2fc of the sender.
+		fn set_approval_for_all(origin, operator: T::AccountId, approved: bool) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::set_approval_for_all(operator.clone(), approved))?;
+			ensure!(operator != sender, "You can't approve yourself");
+
+			if approved {
+				<OperatorApprovals<T>>::insert((sender.clone(), operator.clone()), true);
+			} else {
+				<OperatorApprovals<T>>::remove((sender.clone(), operator.clone()));
+			}
+
+			Self::deposit_event(RawEvent::ApprovalForAll(sender, operator, approved));
+
+			Ok(())
+		}
+
+		/// Pay the escrow, compensations and sale proceeds of the c2fc to `payout`
+		/// instead of the owner until the c2fc is transferred.
+		fn set_payout(origin, c2fc_id: T::Hash, payout: T::AccountId) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::set_payout(c2fc_id, payout.clone()))?;
+
+			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+			ensure!(owner == sender, "You do not own this c2fc");
+
+			<BucketPayout<T>>::insert(c2fc_id, &payout);
+
+			Self::deposit_event(RawEvent::PayoutSet(owner, c2fc_id, payout));
+
+			Ok(())
+		}
+
+		fn clear_payout(origin, c2fc_id: T::Hash) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::clear_payout(c2fc_id))?;
 
 			let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
 			ensure!(owner == sender, "You do not own this c2fc");
+			ensure!(<BucketPayout<T>>::exists(c2fc_id), "This c2fc has no payout account");
+
+			<BucketPayout<T>>::remove(c2fc_id);
+
+			Self::deposit_event(RawEvent::PayoutCleared(owner, c2fc_id));
+
+			Ok(())
+		}
 
-			Self::transfer_from(sender, to, c2fc_id)?;
+		/// Pay the funds of all c2fc of the sender without own payout to `payout`.
+		fn set_default_payout(origin, payout: T::AccountId) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::set_default_payout(payout.clone()))?;
+
+			<AccountPayout<T>>::insert(&sender, &payout);
+
+			Self::deposit_event(RawEvent::DefaultPayoutSet(sender, payout));
+
+			Ok(())
+		}
+
+		fn clear_default_payout(origin) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::clear_default_payout())?;
+			ensure!(<AccountPayout<T>>::exists(&sender), "You have no default payout account");
+
+			<AccountPayout<T>>::remove(&sender);
+
+			Self::deposit_event(RawEvent::DefaultPayoutCleared(sender));
 
 			Ok(())
 		}
 
 		fn buy_c2fc(origin, c2fc_id: T::Hash, max_price: T::Balance) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::buy_c2fc(c2fc_id, max_price))?;
 
 			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
 
@@ -435,12 +926,17 @@ decl_module! {
 			ensure!(owner != sender, "You can't buy your own c2fc");
 
 			let mut c2fc = Self::c2fc(c2fc_id);
-
 			let c2fc_price = c2fc.price;
 			ensure!(!c2fc_price.is_zero(), "The c2fc you want to buy is not for sale");
 			ensure!(c2fc_price <= max_price, "The c2fc you want to buy costs more than your max price");
 
-			Self::transfer_money(&sender, &owner, c2fc_price)?;
+			// the fee is deducted from the price:
+			let fee = Self::parameters().sale_fee * c2fc_price;
+			// the c2fc is transferred after the payment, which isn't rolled back:
+			Self::ensure_can_transfer(&owner, &sender, c2fc_id)?;
+			Self::ensure_can_pay(&sender, c2fc_price - fee, fee)?;
+			Self::hold_proceeds(&sender, &Self::payout_account(c2fc_id, &owner), c2fc_id, c2fc_price - fee)?;
+			Self::charge_fee(&sender, c2fc_id, fee)?;
 			Self::transfer_from(owner.clone(), sender.clone(), c2fc_id)?;
 
 			c2fc.price = T::Balance::zero();
@@ -451,11 +947,28 @@ decl_module! {
 			Ok(())
 		}
 
+		/// Pay the sale proceeds held for the sender out of the escrow.
+		fn claim_proceeds(origin) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::claim_proceeds())?;
+
+			let proceeds = Self::proceeds_of(&sender);
+			ensure!(!proceeds.is_zero(), "No proceeds to claim");
+
+			Self::transfer_money(&Self::escrow_account(), &sender, proceeds)?;
+
+			<SaleProceeds<T>>::remove(&sender);
+			<EscrowTotal<T>>::mutate(|total| *total -= proceeds);
+
+			Self::deposit_event(RawEvent::ProceedsClaimed(sender, proceeds));
+
+			Ok(())
+		}
+
 
 		// do/fill the promises //
 
 		fn fill_c2fc(origin, c2fc_id: T::Hash, deposit: T::Balance) -> Result {
-			let sender = ensure_signed(origin)?;
+			let sender = Self::ensure_allowed(origin, &Call::fill_c2fc(c2fc_id, deposit))?;
 
 			ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
 
@@ -469,16 +982,30 @@ decl_module! {
 			if let Some(ref mut promise) = c2fc.promise {
 				let promise_id = promise.id;
 
-				ensure!(!promise.value.is_zero(), "The promise in the c2fc you want to fill is invalid");
-				ensure!(promise.filled <= promise.value, "The c2fc you want to fill is already fullfilled");
-
-				Self::transfer_money(&sender, &owner, deposit)?;
+				ensure!(!promise.value.is_zero() || promise.revenue_share.is_some(), "The promise in the c2fc you want to fill is invalid");
+				// the deposit is counted to the oldest not closed period:
+				let due = Self::due_of_period(promise_id, promise, Self::closed_periods(promise_id));
+				// missed deposits of previous periods are paid back first:
+				let arrears = Self::arrears_of_promise(promise_id);
+				ensure!(promise.filled < due || !arrears.is_zero(), "The c2fc you want to fill is already fullfilled");
+
+				// the fee is paid on top of the deposit:
+				let fee = Self::parameters().fill_fee * deposit;
+				Self::ensure_can_pay(&sender, deposit, fee)?;
+				// the deposit is held until the end of the current period:
+				Self::hold_in_escrow(&sender, c2fc_id, promise_id, deposit)?;
+				Self::charge_fee(&sender, c2fc_id, fee)?;
+
+				let repaid = if deposit < arrears { deposit } else { arrears };
+				if !repaid.is_zero() {
+					<PromiseArrears<T>>::insert(promise_id, arrears - repaid);
+				}
 
-				promise.filled = deposit + promise.filled;
+				promise.filled = deposit - repaid + promise.filled;
 
 				Self::deposit_event(RawEvent::PromiseFilled(c2fc_id, promise_id, deposit));
 
-				if promise.filled >= promise.value {
+				if promise.filled >= due {
 					Self::deposit_event(RawEvent::PromiseFullilled(c2fc_id, promise_id));
 				}
 			}
@@ -494,7 +1021,11 @@ decl_module! {
 				ensure!(<Buckets<T>>::exists(c2fc_id), "This c2fc does not exist");
 				let c2fc = Self::c2fc(c2fc_id);
 				let promise = &c2fc.promise.ok_or("This c2fc doesnt contains an accepted promise")?;
-				let deposit = promise.filled - promise.value;
+				// the arrears and the rest of the oldest not closed period, as `fill_c2fc` counts it:
+				let due = Self::due_of_period(promise.id, promise, Self::closed_periods(promise.id));
+				let missing = if promise.filled < due { due - promise.filled } else { Zero::zero() };
+				let deposit = Self::arrears_of_promise(promise.id) + missing;
+				ensure!(!deposit.is_zero(), "The c2fc you want to fill is already fullfilled");
 				deposit
 			};
 
@@ -503,251 +1034,2990 @@ decl_module! {
 
 
 
-		/// Check the breach of promise at end of the each block.
-		/// Simple timer here.
-		fn on_finalize(n: T::BlockNumber) {
-			let accepted_promises_count = Self::accepted_promises_count();
-
-			for i in 0..accepted_promises_count {
-				let promise_id = Self::accepted_promise_by_index(i);
-				let c2fc_id = Self::c2fc_by_promise(promise_id);
-
-				if <Buckets<T>>::exists(c2fc_id) {
-					let c2fc = Self::c2fc(c2fc_id);
-					// skip if c2fc doesn't contains a promise
-					if let Some(promise) = &c2fc.promise {
-						let lifetime = n - promise.acception_dt;
-						// fix attempt to subtract with overflow:
-						// let wanted_deposit = (promise.filled.into() as i128) - (promise.value.into() as i128);
-						let wanted_deposit = if promise.filled > promise.value {
-							Some(promise.value - promise.value)
-						} else { None };
-
-						// if (lifetime % promise.period).is_zero() && !wanted_deposit.is_zero() {
-						if let (Some(wanted_deposit), true) = (wanted_deposit, (lifetime % promise.period).is_zero()) {
-							// TODO: reset `promise.filled` to zero because new period starts.
-
-							if wanted_deposit > <T::Balance>::zero() {
-								// here we should to emit Event about *failed promise*.
-								Self::deposit_event(RawEvent::PromiseBreached(c2fc_id, promise_id, wanted_deposit));
-								// TODO: slash the stake
-								// <BucketContributor<T>>::...(c2fc_id,);
-							}
-						}
-					}
-				}
-			}
+		// revenue oracles //
+
+		fn add_oracle(origin, oracle: T::AccountId) -> Result {
+			ensure_root(origin)?;
+			ensure!(!Self::is_oracle(&oracle), "This account is already an oracle");
+
+			<Oracles<T>>::insert(&oracle, true);
+
+			Self::deposit_event(RawEvent::OracleAdded(oracle));
+
+			Ok(())
 		}
-	}
-}
 
+		fn remove_oracle(origin, oracle: T::AccountId) -> Result {
+			ensure_root(origin)?;
+			ensure!(Self::is_oracle(&oracle), "This account is not an oracle");
 
-// private & utils //
+			<Oracles<T>>::remove(&oracle);
 
-fn get_lock<T: Trait>(
-	who: &T::AccountId,
-	lock_id: &LockIdentifier,
-) -> Option<BalanceLock<T::Balance, T::BlockNumber>> {
-	let locks_all = <balances::Module<T>>::locks(who);
-	let mut locks = locks_all
-		.into_iter()
-		.filter_map(|l| if &l.id == lock_id { Some(l) } else { None });
-	locks.next()
-}
+			Self::deposit_event(RawEvent::OracleRemoved(oracle));
 
+			Ok(())
+		}
 
-impl<T: Trait> Module<T> {
 
-	/// Create LockIdentifier via simple counter `locks_count`.
-	/// Previously was by promise_id.
-	fn next_free_lock_identifier(_promise_id: &T::Hash) -> LockIdentifier {
-		use core::mem::size_of;
-		let locks_count = Self::locks_count() + 1;
-		let lid: [u8; size_of::<u64>()] = LockIdentifier::from(locks_count.to_le_bytes());
-		lid
-	}
+		// KYC attestations //
 
-	fn mint_c2fc(
-		to: T::AccountId,
-		c2fc_id: T::Hash,
-		new_c2fc: Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber>,
-	) -> Result {
-		ensure!(!<BucketOwner<T>>::exists(c2fc_id), "Bucket already exists");
+		fn add_attestor(origin, attestor: T::AccountId) -> Result {
+			ensure_root(origin)?;
+			ensure!(!Self::is_attestor(&attestor), "This account is already an attestor");
 
-		let owned_c2fc_count = Self::owned_c2fc_count(&to);
+			<Attestors<T>>::insert(&attestor, true);
 
-		let new_owned_c2fc_count = owned_c2fc_count
-			.checked_add(1)
-			.ok_or("Overflow adding a new c2fc to account balance")?;
+			Self::deposit_event(RawEvent::AttestorAdded(attestor));
 
-		let all_c2fc_count = Self::all_c2fc_count();
+			Ok(())
+		}
 
-		let new_all_c2fc_count = all_c2fc_count
-			.checked_add(1)
-			.ok_or("Overflow adding a new c2fc to total supply")?;
+		fn remove_attestor(origin, attestor: T::AccountId) -> Result {
+			ensure_root(origin)?;
+			ensure!(Self::is_attestor(&attestor), "This account is not an attestor");
 
-		<Buckets<T>>::insert(c2fc_id, new_c2fc);
-		<BucketOwner<T>>::insert(c2fc_id, &to);
+			<Attestors<T>>::remove(&attestor);
 
-		<AllBucketsArray<T>>::insert(all_c2fc_count, c2fc_id);
-		<AllBucketsCount<T>>::put(new_all_c2fc_count);
-		<AllBucketsIndex<T>>::insert(c2fc_id, all_c2fc_count);
+			Self::deposit_event(RawEvent::AttestorRemoved(attestor));
 
-		<OwnedBucketsArray<T>>::insert((to.clone(), owned_c2fc_count), c2fc_id);
-		<OwnedBucketsCount<T>>::insert(&to, new_owned_c2fc_count);
-		<OwnedBucketsIndex<T>>::insert(c2fc_id, owned_c2fc_count);
+			Ok(())
+		}
 
-		Self::deposit_event(RawEvent::C2fcCreated(to, c2fc_id));
+		/// Verify the account until the `expires_at` block, called by root or an attestor.
+		/// Previous attestation of the account is replaced.
+		fn attest(origin, who: T::AccountId, doc_hash: T::Hash, expires_at: T::BlockNumber) -> Result {
+			let attestor = Self::ensure_attestor(origin)?;
+			ensure!(expires_at > <system::Module<T>>::block_number(), "Attestation should expire in the future");
 
-		Ok(())
-	}
+			<Attestations<T>>::insert(&who, Attestation { attestor, doc_hash, expires_at });
 
-	fn mint_promise(
-		to: T::AccountId,
-		promise_id: T::Hash,
-		new_promise: FreePromise<T::Hash, T::Balance, T::BlockNumber>,
-	) -> Result {
-		ensure!(!<PromiseOwner<T>>::exists(promise_id), "Promise already exists");
+			Self::deposit_event(RawEvent::Attested(who, doc_hash, expires_at));
 
-		let owned_promise_count = Self::owned_promise_count(&to);
+			Ok(())
+		}
 
-		let new_owned_promise_count = owned_promise_count
-			.checked_add(1)
-			.ok_or("Overflow adding a new promise to account balance")?;
+		fn revoke_attestation(origin, who: T::AccountId) -> Result {
+			Self::ensure_attestor(origin)?;
+			ensure!(<Attestations<T>>::exists(&who), "This account is not attested");
 
-		let free_promises_count = Self::free_promises_count();
+			<Attestations<T>>::remove(&who);
 
-		let new_free_promises_count = free_promises_count
-			.checked_add(1)
-			.ok_or("Overflow adding a new promise to total supply")?;
+			Self::deposit_event(RawEvent::AttestationRevoked(who));
 
-		<Promises<T>>::insert(promise_id, new_promise);
-		<PromiseOwner<T>>::insert(promise_id, &to);
+			Ok(())
+		}
 
-		<FreePromisesArray<T>>::insert(free_promises_count, promise_id);
-		<FreePromisesCount<T>>::put(new_free_promises_count);
-		<FreePromisesIndex<T>>::insert(promise_id, free_promises_count);
 
-		<OwnedPromisesArray<T>>::insert((to.clone(), owned_promise_count), promise_id);
-		<OwnedPromisesCount<T>>::insert(&to, new_owned_promise_count);
-		<OwnedPromisesIndex<T>>::insert(promise_id, owned_promise_count);
+		/// Report the revenue of the issuer for the current period of accepted revenue-share promise.
+		/// Report the revenue of the current period or of the ended period which isn't closed yet.
+		fn report_revenue(origin, promise_id: T::Hash, period_index: T::BlockNumber, revenue: T::Balance) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::report_revenue(promise_id, period_index, revenue))?;
+			ensure!(Self::is_oracle(&sender), "Only oracles can report the revenue");
+
+			ensure!(<AcceptedPromiseBucket<T>>::exists(promise_id), "This promise is not accepted");
+			let c2fc = Self::c2fc(Self::c2fc_by_promise(promise_id));
+			let promise = c2fc.promise.ok_or("Bucket doesnt contains promise")?;
+			ensure!(promise.revenue_share.is_some(), "This promise does not share the revenue");
+
+			let current_index = Self::current_period_index(&promise).ok_or("This promise has no periods")?;
+			ensure!(period_index <= current_index, "This period hasn't started yet");
+			ensure!(Self::period_count(&promise).map_or(true, |count| period_index < count),
+			        "This period is after the end of the promise");
+			ensure!(period_index >= Self::closed_periods(promise_id), "This period is already closed");
+			ensure!(Self::revenue_report((promise_id, period_index)).is_none(), "The revenue for this period is already reported");
+
+			<RevenueReports<T>>::insert((promise_id, period_index), RevenueReport {
+				oracle: sender,
+				revenue,
+				disputed: false,
+			});
 
-		Self::deposit_event(RawEvent::PromiseCreated(to, promise_id));
+			Self::deposit_event(RawEvent::RevenueReported(promise_id, period_index, revenue));
 
-		Ok(())
-	}
+			Ok(())
+		}
 
-	fn transfer_from(from: T::AccountId, to: T::AccountId, c2fc_id: T::Hash) -> Result {
-		let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+		/// Issuer or owner of the c2fc can dispute the reported revenue.
+		fn dispute_revenue(origin, promise_id: T::Hash, period_index: T::BlockNumber) -> Result {
+			let sender = Self::ensure_allowed(origin, &Call::dispute_revenue(promise_id, period_index))?;
 
-		ensure!(owner == from, "'from' account does not own this c2fc");
+			ensure!(<AcceptedPromiseBucket<T>>::exists(promise_id), "This promise is not accepted");
+			let c2fc_id = Self::c2fc_by_promise(promise_id);
+			let issuer = Self::owner_of_promise(promise_id);
+			let owner = Self::owner_of_c2fc(c2fc_id);
+			ensure!(issuer.as_ref() == Some(&sender) || owner.as_ref() == Some(&sender),
+			        "Only issuer or owner of the c2fc can dispute the revenue");
 
-		let owned_c2fc_count_from = Self::owned_c2fc_count(&from);
-		let owned_c2fc_count_to = Self::owned_c2fc_count(&to);
+			let mut report = Self::revenue_report((promise_id, period_index)).ok_or("The revenue for this period is not reported")?;
+			ensure!(!report.disputed, "The revenue for this period is already disputed");
+			// arrears of the closed period are already settled:
+			ensure!(period_index >= Self::closed_periods(promise_id), "This period is already closed");
 
-		let new_owned_c2fc_count_to = owned_c2fc_count_to
-			.checked_add(1)
-			.ok_or("Transfer causes overflow of 'to' c2fc balance")?;
+			report.disputed = true;
+			<RevenueReports<T>>::insert((promise_id, period_index), report);
+			<OpenDisputes<T>>::mutate(promise_id, |count| *count += 1);
 
-		let new_owned_c2fc_count_from = owned_c2fc_count_from
-			.checked_sub(1)
-			.ok_or("Transfer causes underflow of 'from' c2fc balance")?;
+			Self::deposit_event(RawEvent::RevenueDisputed(promise_id, period_index, sender));
 
-		// "Swap and pop"
-		let c2fc_index = <OwnedBucketsIndex<T>>::get(c2fc_id);
-		if c2fc_index != new_owned_c2fc_count_from {
-			let last_c2fc_id = <OwnedBucketsArray<T>>::get((from.clone(), new_owned_c2fc_count_from));
-			<OwnedBucketsArray<T>>::insert((from.clone(), c2fc_index), last_c2fc_id);
-			<OwnedBucketsIndex<T>>::insert(last_c2fc_id, c2fc_index);
+			Ok(())
 		}
 
-		<BucketOwner<T>>::insert(&c2fc_id, &to);
-		<OwnedBucketsIndex<T>>::insert(c2fc_id, owned_c2fc_count_to);
+		fn resolve_dispute(origin, promise_id: T::Hash, period_index: T::BlockNumber, revenue: T::Balance) -> Result {
+			ensure_root(origin)?;
 
-		<OwnedBucketsArray<T>>::remove((from.clone(), new_owned_c2fc_count_from));
-		<OwnedBucketsArray<T>>::insert((to.clone(), owned_c2fc_count_to), c2fc_id);
+			let mut report = Self::revenue_report((promise_id, period_index)).ok_or("The revenue for this period is not reported")?;
+			ensure!(report.disputed, "The revenue for this period is not disputed");
 
-		<OwnedBucketsCount<T>>::insert(&from, new_owned_c2fc_count_from);
-		<OwnedBucketsCount<T>>::insert(&to, new_owned_c2fc_count_to);
+			report.revenue = revenue;
+			report.disputed = false;
+			<RevenueReports<T>>::insert((promise_id, period_index), report);
 
-		Self::deposit_event(RawEvent::Transferred(from, to, c2fc_id));
+			// the disputed period and the escrow are settled at the next close:
+			let open = Self::open_disputes(promise_id).saturating_sub(1);
+			if open == 0 {
+				<OpenDisputes<T>>::remove(promise_id);
+			} else {
+				<OpenDisputes<T>>::insert(promise_id, open);
+			}
 
-		Ok(())
-	}
+			Self::deposit_event(RawEvent::DisputeResolved(promise_id, period_index, revenue));
+
+			// there is no next close after the last period:
+			if open == 0 {
+				Self::close_ended_promise(promise_id);
+			}
+
+			Ok(())
+		}
+
+
+		// insurance //
+
+		fn set_insurance(origin, enabled: bool, premium: Permill, contribution: Permill, coverage: T::Balance) -> Result {
+			ensure_root(origin)?;
+
+			<InsuranceEnabled<T>>::put(enabled);
+			<InsurancePremium<T>>::put(premium);
+			<InsuranceContribution<T>>::put(contribution);
+			<InsuranceCoverage<T>>::put(coverage);
+
+			Self::deposit_event(RawEvent::InsuranceSet(enabled, premium, contribution, coverage));
+
+			Ok(())
+		}
+
+
+		// governance //
+
+		fn set_parameters(origin, parameters: ParametersOf<T>) -> Result {
+			ensure_root(origin)?;
+
+			ensure!(!parameters.min_period.is_zero(), "Min period should be greater than zero");
+			ensure!(parameters.min_period <= parameters.max_period, "Min period should not exceed max period");
+
+			<Parameters<T>>::put(&parameters);
+
+			Self::deposit_event(RawEvent::ParametersSet(parameters));
+
+			Ok(())
+		}
+
+
+		/// Reject the calls of the category until `unpause`.
+		fn pause(origin, category: PauseCategory) -> Result {
+			ensure_root(origin)?;
+			ensure!(!Self::is_paused(category), "This category is already paused");
+
+			<PausedCategories<T>>::insert(category, true);
+
+			Self::deposit_event(RawEvent::Paused(category));
+
+			Ok(())
+		}
+
+		fn unpause(origin, category: PauseCategory) -> Result {
+			ensure_root(origin)?;
+			ensure!(Self::is_paused(category), "This category is not paused");
+
+			<PausedCategories<T>>::remove(category);
+
+			Self::deposit_event(RawEvent::Unpaused(category));
+
+			Ok(())
+		}
+
+
+		/// Put the account on the deny list, it can only pay toward its existing obligations.
+		fn deny_account(origin, who: T::AccountId) -> Result {
+			ensure_root(origin)?;
+			ensure!(!Self::is_denied(&who), "This account is already on the deny list");
+
+			<DenyList<T>>::insert(&who, true);
+
+			Self::deposit_event(RawEvent::AccountDenied(who));
+
+			Ok(())
+		}
+
+		fn allow_account(origin, who: T::AccountId) -> Result {
+			ensure_root(origin)?;
+			ensure!(Self::is_denied(&who), "This account is not on the deny list");
+
+			<DenyList<T>>::remove(&who);
+
+			Self::deposit_event(RawEvent::AccountAllowed(who));
+
+			Ok(())
+		}
+
+
+		/// Check the consistency of the storage,
+		/// each broken invariant is reported by `InvariantViolated` event.
+		fn check_invariants(origin) -> Result {
+			ensure_root(origin)?;
+
+			let violations = Self::invariant_violations();
+			let count = violations.len() as u32;
+			for violation in violations {
+				Self::deposit_event(RawEvent::InvariantViolated(violation));
+			}
+
+			Self::deposit_event(RawEvent::InvariantsChecked(count));
+
+			Ok(())
+		}
+
+
+		/// Close the overdue period of the promise.
+		/// Submitted by the offchain worker when `on_finalize` missed the end of the period.
+		fn close_overdue_period(origin, promise_id: T::Hash) -> Result {
+			ensure_inherent(origin)?;
+			Self::ensure_storage_migrated()?;
+
+			let now = <system::Module<T>>::block_number();
+			ensure!(Self::is_period_overdue(promise_id, now), "This promise has no overdue periods");
+
+			Self::close_period(promise_id, now);
+
+			Ok(())
+		}
+
+
+		fn on_initialize(n: T::BlockNumber) {
+			Self::migrate_storage(n);
+		}
+
+		/// Check the breach of promise at end of the each block.
+		/// Simple timer here.
+		fn on_finalize(n: T::BlockNumber) {
+			if !Self::is_storage_migrated() {
+				// the entries aren't readable yet:
+				<LastFinalized<T>>::put(n);
+				return;
+			}
+
+			// closing of the promise changes the accepted promises:
+			for promise_id in <AcceptedPromises<T>>::items() {
+				if !Self::close_period(promise_id, n) {
+					Self::close_ended_promise(promise_id);
+				}
+			}
+
+			<LastFinalized<T>>::put(n);
+		}
+
+		/// Warn about promises which are close to the deadline but not filled
+		/// and nudge the chain to close the overdue periods.
+		fn offchain_worker(n: T::BlockNumber) {
+			if !Self::is_storage_migrated() {
+				return;
+			}
+
+			for promise_id in <AcceptedPromises<T>>::items() {
+
+				if Self::is_period_overdue(promise_id, n) {
+					runtime_io::print("C2FC: period of the promise is overdue, closing");
+					runtime_io::print(promise_id.as_ref());
+					if T::SubmitTransaction::submit_unsigned(Call::close_overdue_period(promise_id)).is_err() {
+						runtime_io::print("C2FC: failed to submit the transaction");
+					}
+					continue;
+				}
+
+				let c2fc = Self::c2fc(Self::c2fc_by_promise(promise_id));
+				let promise = match c2fc.promise {
+					Some(promise) => promise,
+					None => continue,
+				};
+				if promise.period.is_zero() {
+					continue;
+				}
+
+				let period_index = Self::closed_periods(promise_id);
+				let deadline = promise.acception_dt + (period_index + One::one()) * promise.period;
+				let due = Self::due_of_period(promise_id, &promise, period_index);
+				if promise.filled < due && deadline > n && deadline - n <= T::BlockNumber::sa(DEADLINE_WARNING_BLOCKS) {
+					runtime_io::print("C2FC: promise is close to the deadline and not filled");
+					runtime_io::print(promise_id.as_ref());
+				}
+			}
+		}
+	}
+}
+
+
+// private & utils //
+
+fn get_lock<T: Trait>(
+	who: &T::AccountId,
+	lock_id: &LockIdentifier,
+) -> Option<BalanceLock<T::Balance, T::BlockNumber>> {
+	let locks_all = <balances::Module<T>>::locks(who);
+	let mut locks = locks_all
+		.into_iter()
+		.filter_map(|l| if &l.id == lock_id { Some(l) } else { None });
+	locks.next()
+}
+
+/// Free balance of the account above its largest lock, the stake isn't usable for payments.
+fn usable_balance<T: Trait>(who: &T::AccountId) -> T::Balance {
+	let free = <balances::Module<T> as Currency<T::AccountId>>::free_balance(who);
+	let locked = <balances::Module<T>>::locks(who)
+		.into_iter()
+		.map(|l| l.amount)
+		.max()
+		.unwrap_or_else(Zero::zero);
+	if free > locked { free - locked } else { Zero::zero() }
+}
+
+
+impl<T: Trait> Module<T> {
+
+	/// Create LockIdentifier via simple counter `locks_count`.
+	/// Previously was by promise_id.
+	fn next_free_lock_identifier(_promise_id: &T::Hash) -> LockIdentifier {
+		use core::mem::size_of;
+		let locks_count = Self::locks_count() + 1;
+		let lid: [u8; size_of::<u64>()] = LockIdentifier::from(locks_count.to_le_bytes());
+		lid
+	}
+
+	fn mint_c2fc(
+		to: T::AccountId,
+		c2fc_id: T::Hash,
+		new_c2fc: Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber>,
+	) -> Result {
+		ensure!(!<BucketOwner<T>>::exists(c2fc_id), "Bucket already exists");
+
+		<AllBuckets<T>>::insert(&c2fc_id)?;
+		<OwnedBuckets<T>>::insert(&to, &c2fc_id)?;
+
+		<Buckets<T>>::insert(c2fc_id, new_c2fc);
+		<BucketOwner<T>>::insert(c2fc_id, &to);
+
+		Self::deposit_event(RawEvent::C2fcCreated(to, c2fc_id));
+
+		Ok(())
+	}
+
+	fn mint_promise(
+		to: T::AccountId,
+		promise_id: T::Hash,
+		new_promise: FreePromise<T::Hash, T::Balance, T::BlockNumber>,
+	) -> Result {
+		ensure!(!<PromiseOwner<T>>::exists(promise_id), "Promise already exists");
+
+		<FreePromises<T>>::insert(&promise_id)?;
+		<OwnedPromises<T>>::insert(&to, &promise_id)?;
+
+		<Promises<T>>::insert(promise_id, new_promise);
+		<PromiseOwner<T>>::insert(promise_id, &to);
+
+		Self::deposit_event(RawEvent::PromiseCreated(to, promise_id));
+
+		Ok(())
+	}
+
+	/// Put the free promise into the c2fc, the periods start at `now`.
+	fn attach_promise(promise_id: T::Hash, c2fc_id: T::Hash, now: T::BlockNumber) -> Result {
+		let issuer = Self::ensure_can_attach(promise_id, c2fc_id)?;
+
+		let mut c2fc = Self::c2fc(c2fc_id);
+		let free_promise = Self::promise(promise_id);
+		c2fc.promise = Some(Promise {
+			id: free_promise.id,
+			// in the near future `owner` can be removed
+			owner: issuer.clone(),
+			value: free_promise.value,
+			period: free_promise.period,
+			until: free_promise.until,
+			acception_dt: now,
+			filled: T::Balance::zero(),
+			revenue_share: free_promise.revenue_share,
+		});
+
+		// push to maps:
+		<FreePromises<T>>::remove(&promise_id)?;
+		<AcceptedPromises<T>>::insert(&promise_id)?;
+		<IssuerPromises<T>>::insert(&issuer, &promise_id)?;
+
+		<Buckets<T>>::insert(c2fc_id, c2fc);
+		<AcceptedPromiseBucket<T>>::insert(promise_id, c2fc_id);
+		<BucketContributor<T>>::insert(c2fc_id, issuer);
+
+		Ok(())
+	}
+
+	/// Check the indexes of free promise before anything is written by `attach_promise`.
+	/// Returns the issuer.
+	fn ensure_can_attach(promise_id: T::Hash, c2fc_id: T::Hash) -> result::Result<T::AccountId, &'static str> {
+		ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
+		ensure!(!<AcceptedPromiseBucket<T>>::exists(promise_id), "This promise is already accepted");
+		let issuer = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
+		let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+		ensure!(issuer != owner, "You can not accept your own promise");
+		ensure!(Self::c2fc(c2fc_id).promise.is_none(), "Bucket already contains another promise");
+
+		ensure!(<FreePromises<T>>::contains(&promise_id), "This promise is not indexed as free");
+		ensure!(!<AcceptedPromises<T>>::contains(&promise_id) && !<IssuerPromises<T>>::contains(&promise_id),
+		        "This promise is already indexed as accepted");
+		Ok(issuer)
+	}
+
+	/// Lock `amount` of the issuer behind the promise without a stake
+	/// until the end of the promise.
+	fn lock_stake(promise_id: T::Hash, amount: T::Balance) -> Result {
+		let issuer = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
+		ensure!(!<LockForPromise<T>>::exists(promise_id), "This promise already has a stake");
+
+		let until = if <AcceptedPromiseBucket<T>>::exists(promise_id) {
+			Self::c2fc(Self::c2fc_by_promise(promise_id)).promise.and_then(|promise| promise.until)
+		} else {
+			Self::promise(promise_id).until
+		}.unwrap_or_else(|| T::BlockNumber::sa(u64::max_value()));
+
+		let lock_id = Self::next_free_lock_identifier(&promise_id);
+		let reasons = WithdrawReasons::from(WithdrawReason::Reserve);
+		<balances::Module<T>>::set_lock(lock_id, &issuer, amount, until, reasons);
+
+		<LockForPromise<T>>::insert(promise_id, lock_id);
+		<LocksCount<T>>::mutate(|n| *n += 1);
+
+		Self::deposit_event(RawEvent::Stake(promise_id, issuer, amount));
+
+		Ok(())
+	}
+
+	fn transfer_from(from: T::AccountId, to: T::AccountId, c2fc_id: T::Hash) -> Result {
+		Self::ensure_can_transfer(&from, &to, c2fc_id)?;
+
+		<OwnedBuckets<T>>::transfer(&from, &to, &c2fc_id)?;
+		<BucketOwner<T>>::insert(&c2fc_id, &to);
+		// approvals and payout of the previous owner:
+		if <BucketApprovals<T>>::exists(c2fc_id) {
+			<BucketApprovals<T>>::remove(c2fc_id);
+			Self::deposit_event(RawEvent::ApprovalCleared(from.clone(), c2fc_id));
+		}
+		if <BucketPayout<T>>::exists(c2fc_id) {
+			<BucketPayout<T>>::remove(c2fc_id);
+			Self::deposit_event(RawEvent::PayoutCleared(from.clone(), c2fc_id));
+		}
+
+		Self::deposit_event(RawEvent::Transferred(from, to, c2fc_id));
+
+		Ok(())
+	}
+
+	/// Check the transfer of the c2fc before anything is written.
+	fn ensure_can_transfer(from: &T::AccountId, to: &T::AccountId, c2fc_id: T::Hash) -> Result {
+		let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+		ensure!(owner == *from, "'from' account does not own this c2fc");
+		ensure!(<OwnedBuckets<T>>::contains_in(from, &c2fc_id), "This c2fc is not indexed");
+		ensure!(!<OwnedBuckets<T>>::contains_in(to, &c2fc_id), "This c2fc is already indexed by the recipient");
+		if let Some(promise) = Self::c2fc(c2fc_id).promise {
+			if Self::promise(promise.id).verified_only {
+				ensure!(Self::is_verified(to), "Only verified accounts can own this c2fc");
+			}
+		}
+		Ok(())
+	}
+
+	/// Whether the account is the owner of the c2fc, approved for it or an operator of the owner.
+	pub fn can_manage_c2fc(who: &T::AccountId, owner: &T::AccountId, c2fc_id: T::Hash) -> bool {
+		who == owner ||
+			Self::approved_for_c2fc(c2fc_id).as_ref() == Some(who) ||
+			Self::is_approved_for_all((owner.clone(), who.clone()))
+	}
+
+	/// Account receiving the funds paid to the owner of the c2fc:
+	/// payout of the c2fc, default payout of the owner or the owner itself.
+	/// Listed payouts are skipped.
+	pub fn payout_account(c2fc_id: T::Hash, owner: &T::AccountId) -> T::AccountId {
+		Self::payout_of_c2fc(c2fc_id)
+			.filter(|payout| !Self::is_denied(payout))
+			.or_else(|| Self::default_payout_of(owner).filter(|payout| !Self::is_denied(payout)))
+			.unwrap_or_else(|| owner.clone())
+	}
 
 	fn transfer_money(from: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> Result {
 		<balances::Module<T> as Currency<T::AccountId>>::transfer(&from, &to, amount)
 	}
 
+	fn create_free_promise(
+		sender: T::AccountId,
+		value: T::Balance,
+		period: T::BlockNumber,
+		until: T::BlockNumber,
+		revenue_share: Option<Permill>,
+		verified_only: bool,
+	) -> Result {
+		Self::ensure_promise_terms(value, period)?;
+		if verified_only {
+			ensure!(Self::is_verified(&sender), "Only verified accounts can issue this promise");
+		}
+		ensure!(Self::open_promise_count(&sender) < Self::parameters().max_promises_per_account,
+		        "Too many promises of the account");
 
-	// utilites //
+		let nonce = <Nonce<T>>::get();
+		let promise_id = (<system::Module<T>>::random_seed(), &sender, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
 
-	#[inline]
-	pub fn is_promise_accepted(promise_id: T::Hash) -> result::Result<bool, &'static str> {
-		ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
-		Ok(<AcceptedPromiseBucket<T>>::exists(promise_id))
+		let new_promise = FreePromise {
+			id: promise_id,
+			value,
+			period,
+			until: if !until.is_zero() { Some(until) } else { None },
+			revenue_share,
+			verified_only,
+		};
+
+		Self::mint_promise(sender, promise_id, new_promise)?;
+
+		<Nonce<T>>::mutate(|n| *n += 1);
+
+		Ok(())
 	}
-}
 
+	/// Whether the account has not expired attestation.
+	pub fn is_verified(who: &T::AccountId) -> bool {
+		Self::attestation_of(who).map_or(false, |attestation| {
+			<system::Module<T>>::block_number() < attestation.expires_at
+		})
+	}
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+	/// Root or an attestor, returns the attestor.
+	fn ensure_attestor(origin: T::Origin) -> result::Result<Option<T::AccountId>, &'static str> {
+		let origin: Option<system::RawOrigin<T::AccountId>> = origin.into();
+		match origin {
+			Some(system::RawOrigin::Root) => Ok(None),
+			Some(system::RawOrigin::Signed(who)) => {
+				ensure!(Self::is_attestor(&who), "Only root or attestors can attest accounts");
+				Ok(Some(who))
+			},
+			_ => Err("bad origin: expected to be a root or signed origin"),
+		}
+	}
 
-	use runtime_io::with_externalities;
-	use primitives::{H256, Blake2Hasher};
-	use support::{impl_outer_origin, assert_ok};
-	use runtime_primitives::{
-		BuildStorage,
-		traits::{BlakeTwo256, IdentityLookup},
-		testing::{Digest, DigestItem, Header}
-};
+	/// Whether the calls of the category are paused by itself or by `PauseCategory::All`.
+	pub fn is_category_paused(category: PauseCategory) -> bool {
+		Self::is_paused(PauseCategory::All) || Self::is_paused(category)
+	}
 
-	impl_outer_origin! {
-		pub enum Origin for Test {}
+	/// Dispatch guard of every signed call, returns the sender.
+	fn ensure_allowed(origin: T::Origin, call: &Call<T>) -> result::Result<T::AccountId, &'static str> {
+		let sender = ensure_signed(origin)?;
+		Self::ensure_call_allowed(&sender, call)?;
+		Ok(sender)
 	}
 
-	// For testing the module, we construct most of a mock runtime. This means
-	// first constructing a configuration type (`Test`) which `impl`s each of the
-	// configuration traits of modules we want to use.
-	#[derive(Clone, Eq, PartialEq)]
-	pub struct Test;
-	impl system::Trait for Test {
-		type Origin = Origin;
-		type Index = u64;
-		type BlockNumber = u64;
-		type Hash = H256;
-		type Hashing = BlakeTwo256;
-		type Digest = Digest;
-		type AccountId = u64;
-		type Lookup = IdentityLookup<Self::AccountId>;
-		type Header = Header;
-		type Event = ();
-		type Log = DigestItem;
+	/// Checks of every signed call: the storage is migrated, the call isn't paused
+	/// and the parties of the call aren't on the deny list.
+	/// Also used by the runtime to reject the transaction before the inclusion.
+	pub fn ensure_call_allowed(sender: &T::AccountId, call: &Call<T>) -> Result {
+		Self::ensure_storage_migrated()?;
+		ensure!(!Self::is_call_paused(call), "This call is paused");
+		Self::ensure_not_denied(sender, call)
 	}
-	impl Trait for Test {
-		type Event = ();
+
+	/// Listed accounts can only pay toward their existing obligations,
+	/// any other call of a listed sender is rejected.
+	fn ensure_not_denied(sender: &T::AccountId, call: &Call<T>) -> Result {
+		let counterparties = match call {
+			Call::fill_c2fc(c2fc_id, _) if Self::contributor_of_c2fc(c2fc_id).as_ref() == Some(sender) => return Ok(()),
+			Call::fullfill_c2fc(c2fc_id) if Self::contributor_of_c2fc(c2fc_id).as_ref() == Some(sender) => return Ok(()),
+			Call::authorize_debit(promise_id, _) if <AcceptedPromiseBucket<T>>::exists(promise_id) => return Ok(()),
+			Call::accept_promise(promise_id, _) => vec![Self::owner_of_promise(promise_id)],
+			// approved accounts and operators act for the owner:
+			Call::transfer(to, c2fc_id) => vec![Some(to.clone()), Self::owner_of_c2fc(c2fc_id)],
+			Call::set_price(c2fc_id, _) => vec![Self::owner_of_c2fc(c2fc_id)],
+			Call::approve(approved, c2fc_id) => vec![Some(approved.clone()), Self::owner_of_c2fc(c2fc_id)],
+			Call::set_approval_for_all(operator, _) => vec![Some(operator.clone())],
+			Call::buy_c2fc(c2fc_id, _) => vec![Self::owner_of_c2fc(c2fc_id)],
+			Call::set_payout(_, payout) => vec![Some(payout.clone())],
+			Call::set_default_payout(payout) => vec![Some(payout.clone())],
+			_ => vec![],
+		};
+
+		ensure!(!Self::is_denied(sender), "This account is on the deny list");
+		for counterparty in counterparties.into_iter().flatten() {
+			ensure!(!Self::is_denied(&counterparty), "The counterparty is on the deny list");
+		}
+		Ok(())
 	}
-	type CashflowModule = Module<Test>;
 
-	// This function basically just builds a genesis storage key/value store according to
-	// our desired mockup.
-	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
-		system::GenesisConfig::<Test>::default()
-			.build_storage()
-			.unwrap()
-			.0
-			.into()
+	/// Whether the call is paused, used to reject it before the inclusion.
+	pub fn is_call_paused(call: &Call<T>) -> bool {
+		let category = match call {
+			Call::create_c2fc(..) |
+			Call::create_promise_until(..) |
+			Call::create_revenue_share_promise(..) |
+			Call::create_promise(..) |
+			Call::edit_promise(..) |
+			Call::accept_promise(..) => PauseCategory::Creation,
+			Call::set_price(..) |
+			Call::transfer(..) |
+			Call::approve(..) |
+			Call::clear_approval(..) |
+			Call::set_approval_for_all(..) |
+			Call::set_payout(..) |
+			Call::clear_payout(..) |
+			Call::set_default_payout(..) |
+			Call::clear_default_payout(..) |
+			Call::buy_c2fc(..) |
+			Call::claim_proceeds(..) => PauseCategory::Trading,
+			Call::fill_c2fc(..) |
+			Call::fullfill_c2fc(..) |
+			Call::authorize_debit(..) |
+			Call::revoke_debit(..) => PauseCategory::Filling,
+			Call::stake_to_promise(..) |
+			Call::withdraw_staken(..) => PauseCategory::Staking,
+			Call::report_revenue(..) |
+			Call::dispute_revenue(..) => PauseCategory::Reporting,
+			// root and unsigned calls aren't paused:
+			_ => return false,
+		};
+		Self::is_category_paused(category)
+	}
+
+	/// Check the value and period of the promise against the current parameters.
+	fn ensure_promise_terms(value: T::Balance, period: T::BlockNumber) -> Result {
+		let parameters = Self::parameters();
+		ensure!(period >= parameters.min_period, "Promise period is shorter than the min period");
+		ensure!(period <= parameters.max_period, "Promise period is longer than the max period");
+		ensure!(value <= parameters.max_value, "Promise value exceeds the max value");
+		Ok(())
+	}
+
+	/// Close the oldest not closed period of accepted promise if it is ended:
+	/// collect the automatic debit, register the breach and release the escrow.
+	/// Returns `false` if there is nothing to close.
+	fn close_period(promise_id: T::Hash, now: T::BlockNumber) -> bool {
+		let c2fc_id = Self::c2fc_by_promise(promise_id);
+
+		if !<Buckets<T>>::exists(c2fc_id) {
+			return false;
+		}
+
+		let mut c2fc = Self::c2fc(c2fc_id);
+		let closed_index = Self::closed_periods(promise_id);
+
+		let ended = {
+			// skip if c2fc doesn't contains a promise
+			let promise = match c2fc.promise {
+				Some(ref mut promise) => promise,
+				None => return false,
+			};
+
+			match Self::closable_periods(promise_id, promise, now) {
+				Some(closable) if closable > closed_index => (),
+				_ => return false,
+			}
+
+			let due = Self::due_of_period(promise_id, promise, closed_index);
+
+			// the filling is paused for the automatic debit too, the due amount goes to the arrears:
+			let filling_paused = Self::is_category_paused(PauseCategory::Filling);
+			if promise.filled < due && !filling_paused {
+				if let Some(limit) = Self::debit_authorization(promise_id) {
+					let wanted = due - promise.filled;
+					let wanted = if wanted < limit { wanted } else { limit };
+					promise.filled += Self::auto_debit(c2fc_id, promise_id, &promise.owner, wanted);
+				}
+			}
+
+			if promise.filled < due {
+				let wanted_deposit = due - promise.filled;
+				<PromiseArrears<T>>::mutate(promise_id, |arrears| *arrears += wanted_deposit);
+				// here we should to emit Event about *failed promise*.
+				Self::deposit_event(RawEvent::PromiseBreached(c2fc_id, promise_id, wanted_deposit));
+				// the issuer can't fill while the filling is paused, the breach stays in arrears:
+				if !filling_paused {
+					Self::compensate_breach(c2fc_id, promise_id, &promise.owner, wanted_deposit);
+				}
+			}
+
+			// new period starts:
+			promise.filled = T::Balance::zero();
+
+			// the last period is closed, not just the time is over:
+			Self::period_count(promise).map_or(false, |count| closed_index + One::one() >= count)
+		};
+
+		<ClosedPeriods<T>>::insert(promise_id, closed_index + One::one());
+		<Buckets<T>>::insert(c2fc_id, c2fc);
+		// the escrow is held back until the disputes are resolved,
+		// if the transfer fails the funds are kept for the next period:
+		if ended {
+			// the promise is fulfilled (or breached) till the end:
+			Self::close_ended_promise(promise_id);
+		} else if Self::open_disputes(promise_id) == 0 {
+			let _ = Self::release_escrow(c2fc_id, promise_id);
+		}
+
+		true
+	}
+
+	/// Release the escrow of the promise with all periods closed and close it.
+	/// The promise waits for its disputes, and stays accepted while the escrow can't be released
+	/// (e.g. the owner is on the deny list), so no funds are left behind; `on_finalize` retries it.
+	fn close_ended_promise(promise_id: T::Hash) {
+		if !<AcceptedPromiseBucket<T>>::exists(promise_id) || Self::open_disputes(promise_id) != 0 {
+			return;
+		}
+		let c2fc_id = Self::c2fc_by_promise(promise_id);
+		let ended = Self::c2fc(c2fc_id).promise
+			.and_then(|promise| Self::period_count(&promise))
+			.map_or(false, |count| Self::closed_periods(promise_id) >= count);
+		if ended && Self::release_escrow(c2fc_id, promise_id).is_ok() {
+			let _ = Self::close_promise(c2fc_id, promise_id);
+		}
+	}
+
+	/// Number of periods till the end of the promise, `None` if the promise has no end.
+	/// The period which contains `until` is the last one.
+	fn period_count(promise: &Promise<T::Hash, T::Balance, T::AccountId, T::BlockNumber>) -> Option<T::BlockNumber> {
+		let until = promise.until?;
+		if promise.period.is_zero() || until <= promise.acception_dt {
+			return Some(One::one());
+		}
+		Some((until - promise.acception_dt + promise.period - One::one()) / promise.period)
+	}
+
+	/// Remove ended promise from the c2fc and from the indexes of accepted promises.
+	fn close_promise(c2fc_id: T::Hash, promise_id: T::Hash) -> Result {
+		let issuer = Self::ensure_can_close(promise_id)?;
+
+		<AcceptedPromises<T>>::remove(&promise_id)?;
+		<IssuerPromises<T>>::remove(&issuer, &promise_id)?;
+
+		<AcceptedPromiseBucket<T>>::remove(promise_id);
+		<BucketContributor<T>>::remove(c2fc_id);
+		<ClosedPromises<T>>::insert(promise_id, true);
+		<ClosedPromisesCount<T>>::mutate(&issuer, |count| *count += 1);
+
+		<Buckets<T>>::mutate(c2fc_id, |c2fc| c2fc.promise = None);
+		Self::clear_promise_state(promise_id);
+
+		Self::deposit_event(RawEvent::PromiseClosed(c2fc_id, promise_id));
+
+		Ok(())
+	}
+
+	/// Remove the per-promise items of closed promise, its escrow is already released.
+	/// The stake lock stays until the issuer withdraws it.
+	fn clear_promise_state(promise_id: T::Hash) {
+		let closed = Self::closed_periods(promise_id);
+		let mut period = T::BlockNumber::zero();
+		while period < closed {
+			<RevenueReports<T>>::remove((promise_id, period));
+			period += One::one();
+		}
+		<ClosedPeriods<T>>::remove(promise_id);
+		<PromiseArrears<T>>::remove(promise_id);
+		<DebitAuthorizations<T>>::remove(promise_id);
+		<OpenDisputes<T>>::remove(promise_id);
+		<PromiseEscrow<T>>::remove(promise_id);
+		<InsurancePremiums<T>>::remove(promise_id);
+	}
+
+	/// Number of promises of the issuer which are not closed.
+	pub fn open_promise_count(issuer: &T::AccountId) -> u64 {
+		Self::owned_promise_count(issuer).saturating_sub(Self::closed_promise_count(issuer))
+	}
+
+	/// Check the indexes of accepted promise before anything is removed by `close_promise`.
+	/// Returns the issuer.
+	fn ensure_can_close(promise_id: T::Hash) -> result::Result<T::AccountId, &'static str> {
+		let issuer = Self::owner_of_promise(promise_id).ok_or("No owner for this promise")?;
+		ensure!(<AcceptedPromises<T>>::contains(&promise_id), "This promise is not accepted");
+		ensure!(<IssuerPromises<T>>::contains_in(&issuer, &promise_id), "This promise is not indexed by the issuer");
+		Ok(issuer)
+	}
+
+	/// Number of ended periods of accepted promise at the block `now`.
+	fn ended_periods(promise: &Promise<T::Hash, T::Balance, T::AccountId, T::BlockNumber>, now: T::BlockNumber) -> Option<T::BlockNumber> {
+		if promise.period.is_zero() || now < promise.acception_dt {
+			return None;
+		}
+		Some((now - promise.acception_dt) / promise.period)
+	}
+
+	/// Whether the accepted promise has ended but not closed periods at the block `now`.
+	pub fn is_period_overdue(promise_id: T::Hash, now: T::BlockNumber) -> bool {
+		if !<AcceptedPromiseBucket<T>>::exists(promise_id) {
+			return false;
+		}
+		let c2fc = Self::c2fc(Self::c2fc_by_promise(promise_id));
+		c2fc.promise
+			.and_then(|promise| Self::closable_periods(promise_id, &promise, now))
+			.map(|closable| closable > Self::closed_periods(promise_id))
+			.unwrap_or(false)
+	}
+
+	/// Number of periods of accepted promise which can be closed at the block `now`.
+	/// Revenue-share period is closed after the report grace and not while its revenue is disputed.
+	fn closable_periods(
+		promise_id: T::Hash,
+		promise: &Promise<T::Hash, T::Balance, T::AccountId, T::BlockNumber>,
+		now: T::BlockNumber,
+	) -> Option<T::BlockNumber> {
+		let closable = if promise.revenue_share.is_none() {
+			Self::ended_periods(promise, now)?
+		} else {
+			let grace = Self::parameters().report_grace;
+			if now < grace {
+				return None;
+			}
+			let ended = Self::ended_periods(promise, now - grace)?;
+
+			let closed_index = Self::closed_periods(promise_id);
+			match Self::revenue_report((promise_id, closed_index)) {
+				Some(ref report) if report.disputed => closed_index,
+				_ => ended,
+			}
+		};
+
+		// nothing is due after the end of the promise:
+		match Self::period_count(promise) {
+			Some(count) if closable > count => Some(count),
+			_ => Some(closable),
+		}
+	}
+
+	/// Validation of unsigned calls submitted by the offchain worker.
+	pub fn validate_unsigned(call: &Call<T>) -> TransactionValidity {
+		match call {
+			Call::close_overdue_period(_) if !Self::is_storage_migrated() => {
+				TransactionValidity::Invalid(INVALID_UNSIGNED_CALL)
+			},
+			Call::close_overdue_period(promise_id) => {
+				// unsigned transactions are validated against the state of the last block:
+				let next_block = Self::last_finalized() + One::one();
+				if !Self::is_period_overdue(*promise_id, next_block) {
+					return TransactionValidity::Invalid(INVALID_UNSIGNED_CALL);
+				}
+
+				TransactionValidity::Valid {
+					priority: 0,
+					requires: vec![],
+					provides: vec![(promise_id, Self::closed_periods(promise_id)).encode()],
+					longevity: TransactionLongevity::max_value(),
+				}
+			},
+			_ => TransactionValidity::Invalid(INVALID_UNSIGNED_CALL),
+		}
+	}
+
+	/// Index of the current period of accepted promise.
+	fn current_period_index(promise: &Promise<T::Hash, T::Balance, T::AccountId, T::BlockNumber>) -> Option<T::BlockNumber> {
+		Self::ended_periods(promise, <system::Module<T>>::block_number())
+	}
+
+	/// Amount due for the period of accepted promise.
+	/// Revenue-share promise owes the share of undisputed reported revenue,
+	/// otherwise the fixed `value` is due.
+	pub fn due_of_period(
+		promise_id: T::Hash,
+		promise: &Promise<T::Hash, T::Balance, T::AccountId, T::BlockNumber>,
+		period_index: T::BlockNumber,
+	) -> T::Balance {
+		match (promise.revenue_share, Self::revenue_report((promise_id, period_index))) {
+			(Some(share), Some(ref report)) if !report.disputed => share * report.revenue,
+			_ => promise.value,
+		}
+	}
+
+	/// Account holding the funds under module custody.
+	pub fn escrow_account() -> T::AccountId {
+		Self::module_account(ESCROW_ID)
+	}
+
+	/// Account holding the funds of the insurance pool.
+	pub fn insurance_account() -> T::AccountId {
+		Self::module_account(INSURANCE_ID)
+	}
+
+	/// Account derived from the identifier, so nobody owns the key of it.
+	fn module_account(id: &[u8; 8]) -> T::AccountId {
+		let mut entropy = [0u8; 64];
+		entropy[..id.len()].copy_from_slice(id);
+		T::AccountId::decode(&mut &entropy[..]).unwrap_or_default()
+	}
+
+	fn hold_in_escrow(from: &T::AccountId, c2fc_id: T::Hash, promise_id: T::Hash, amount: T::Balance) -> Result {
+		if amount.is_zero() {
+			return Ok(());
+		}
+
+		Self::transfer_money(from, &Self::escrow_account(), amount)?;
+
+		<BucketEscrow<T>>::mutate(c2fc_id, |held| *held += amount);
+		<PromiseEscrow<T>>::mutate(promise_id, |held| *held += amount);
+		<EscrowTotal<T>>::mutate(|total| *total += amount);
+
+		Self::deposit_event(RawEvent::EscrowHeld(c2fc_id, from.clone(), amount));
+
+		Ok(())
+	}
+
+	/// Hold the sale proceeds for the payout account of the seller until claimed.
+	fn hold_proceeds(buyer: &T::AccountId, payout: &T::AccountId, c2fc_id: T::Hash, amount: T::Balance) -> Result {
+		if amount.is_zero() {
+			return Ok(());
+		}
+
+		Self::transfer_money(buyer, &Self::escrow_account(), amount)?;
+
+		<SaleProceeds<T>>::mutate(payout, |held| *held += amount);
+		<EscrowTotal<T>>::mutate(|total| *total += amount);
+
+		Self::deposit_event(RawEvent::EscrowHeld(c2fc_id, buyer.clone(), amount));
+
+		Ok(())
+	}
+
+	/// Pay everything held for the c2fc to the payout account of its current owner.
+	/// The funds are kept in custody if the transfer fails.
+	fn release_escrow(c2fc_id: T::Hash, promise_id: T::Hash) -> Result {
+		let held = Self::escrow_of_c2fc(c2fc_id);
+		if held.is_zero() {
+			return Ok(());
+		}
+		let owner = Self::owner_of_c2fc(c2fc_id).ok_or("No owner for this c2fc")?;
+		let payout = Self::transfer_to_payout(&Self::escrow_account(), c2fc_id, &owner, held)?;
+
+		<BucketEscrow<T>>::remove(c2fc_id);
+		<PromiseEscrow<T>>::mutate(promise_id, |promise_held| {
+			*promise_held = if *promise_held > held { *promise_held - held } else { T::Balance::zero() }
+		});
+		<EscrowTotal<T>>::mutate(|total| *total -= held);
+
+		Self::deposit_event(RawEvent::EscrowReleased(c2fc_id, payout, held));
+
+		Ok(())
+	}
+
+	/// Transfer to the payout account of the c2fc, or to the owner if the payout can't receive it
+	/// (e.g. the amount is below the existential deposit of a new account). Returns the recipient.
+	fn transfer_to_payout(from: &T::AccountId, c2fc_id: T::Hash, owner: &T::AccountId, amount: T::Balance)
+		-> result::Result<T::AccountId, &'static str>
+	{
+		let payout = Self::payout_account(c2fc_id, owner);
+		if payout != *owner && Self::transfer_money(from, &payout, amount).is_ok() {
+			return Ok(payout);
+		}
+		ensure!(!Self::is_denied(owner), "The payout account is on the deny list");
+		Self::transfer_money(from, owner, amount)?;
+		Ok(owner.clone())
+	}
+
+	/// Deposit to the payout account of the c2fc, or to the owner if the payout can't receive it.
+	/// Returns the deposited amount.
+	fn deposit_to_payout(c2fc_id: T::Hash, owner: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let payout = Self::payout_account(c2fc_id, owner);
+		let deposited = <balances::Module<T> as Currency<T::AccountId>>::deposit_creating(&payout, amount).peek();
+		if deposited.is_zero() && payout != *owner && !Self::is_denied(owner) {
+			return <balances::Module<T> as Currency<T::AccountId>>::deposit_creating(owner, amount).peek();
+		}
+		deposited
+	}
+
+	/// Returns `(tracked, actual)` funds of the escrow account for audit.
+	pub fn escrow_audit() -> (T::Balance, T::Balance) {
+		(Self::escrow_total(), Self::module_account_funds(&Self::escrow_account()))
+	}
+
+	/// Returns `(tracked, actual)` funds of the insurance pool for audit.
+	pub fn insurance_audit() -> (T::Balance, T::Balance) {
+		(Self::insurance_pool(), Self::module_account_funds(&Self::insurance_account()))
+	}
+
+	/// Funds of the module account above the endowment of the genesis.
+	fn module_account_funds(account: &T::AccountId) -> T::Balance {
+		let total = <balances::Module<T> as Currency<T::AccountId>>::total_balance(account);
+		let endowment = <balances::Module<T> as Currency<T::AccountId>>::minimum_balance();
+		if total > endowment { total - endowment } else { Zero::zero() }
+	}
+
+	/// Pull the due amount from the issuer into the escrow of the c2fc.
+	/// Returns debited value, zero if the debit failed.
+	fn auto_debit(c2fc_id: T::Hash, promise_id: T::Hash, issuer: &T::AccountId, due: T::Balance) -> T::Balance {
+		// the fee is paid on top of the due amount, as for the fill,
+		// the locked stake isn't debited:
+		let fee = Self::parameters().fill_fee * due;
+		let usable = usable_balance::<T>(issuer);
+		let debited = match due.checked_add(&fee) {
+			Some(total) if total <= usable => Self::ensure_can_pay(issuer, due, fee),
+			_ => Err("Not enough usable balance"),
+		};
+		let debited = debited
+			.and_then(|_| Self::hold_in_escrow(issuer, c2fc_id, promise_id, due))
+			.and_then(|_| Self::charge_fee(issuer, c2fc_id, fee));
+
+		match debited {
+			Ok(_) => {
+				Self::deposit_event(RawEvent::AutoDebitSucceeded(c2fc_id, promise_id, due));
+				due
+			},
+			Err(_) => {
+				Self::deposit_event(RawEvent::AutoDebitFailed(c2fc_id, promise_id, due));
+				T::Balance::zero()
+			},
+		}
+	}
+
+	/// Pay the missed deposit to the payout account of the c2fc owner from the stake of the issuer
+	/// and then from the insurance pool. Paid compensation is taken off the arrears.
+	fn compensate_breach(c2fc_id: T::Hash, promise_id: T::Hash, issuer: &T::AccountId, shortfall: T::Balance) {
+		let owner = match Self::owner_of_c2fc(c2fc_id) {
+			Some(owner) => owner,
+			None => return,
+		};
+		// the stake isn't slashed for a listed owner, the arrears are kept:
+		if Self::is_denied(&Self::payout_account(c2fc_id, &owner)) {
+			return;
+		}
+
+		let insured = Self::insurance_enabled();
+		let mut compensation = T::Balance::zero();
+
+		// slash the stake:
+		let lock = if <LockForPromise<T>>::exists(promise_id) {
+			get_lock::<T>(issuer, &Self::lock_for_promise(promise_id))
+		} else { None };
+		if let Some(lock) = lock {
+			// with the insurance the contribution to the pool is slashed on top of the shortfall:
+			let contribution = if insured { Self::insurance_contribution() * shortfall } else { T::Balance::zero() };
+			let wanted = shortfall + contribution;
+			let to_slash = if lock.amount < wanted { lock.amount } else { wanted };
+
+			let (imbalance, _) = <balances::Module<T> as Currency<T::AccountId>>::slash(issuer, to_slash);
+			let slashed = imbalance.peek();
+			// the slashed funds are burned here and minted right below for the owner and the pool:
+			drop(imbalance);
+
+			if slashed < lock.amount {
+				<balances::Module<T>>::set_lock(lock.id, issuer, lock.amount - slashed, lock.until, lock.reasons);
+			} else {
+				<balances::Module<T>>::remove_lock(lock.id, issuer);
+				<LockForPromise<T>>::remove(promise_id);
+			}
+			Self::deposit_event(RawEvent::StakeSlashed(promise_id, issuer.clone(), slashed));
+
+			let to_owner = if slashed < shortfall { slashed } else { shortfall };
+			compensation = Self::deposit_to_payout(c2fc_id, &owner, to_owner);
+			// the compensation which can't be deposited is returned to the issuer, the arrears are kept:
+			if compensation < to_owner {
+				let _ = <balances::Module<T> as Currency<T::AccountId>>::deposit_creating(issuer, to_owner - compensation);
+			}
+
+			let excess = slashed - to_owner;
+			if !excess.is_zero() {
+				let funded = <balances::Module<T> as Currency<T::AccountId>>::deposit_creating(&Self::insurance_account(), excess).peek();
+				<InsurancePool<T>>::mutate(|pool| *pool += funded);
+				Self::deposit_event(RawEvent::InsurancePoolFunded(promise_id, funded));
+			}
+		}
+
+		// cover the rest from the pool:
+		let uncovered = shortfall - compensation;
+		if insured && !uncovered.is_zero() {
+			let pool = Self::insurance_pool();
+			let coverage = Self::insurance_coverage();
+			let mut claim = if uncovered < coverage { uncovered } else { coverage };
+			if pool < claim {
+				claim = pool;
+			}
+
+			if !claim.is_zero() && Self::transfer_to_payout(&Self::insurance_account(), c2fc_id, &owner, claim).is_ok() {
+				<InsurancePool<T>>::put(pool - claim);
+				<InsuranceClaims<T>>::mutate(c2fc_id, |claims| *claims += claim);
+				Self::deposit_event(RawEvent::InsuranceClaimPaid(c2fc_id, promise_id, claim));
+				compensation += claim;
+			}
+		}
+
+		if !compensation.is_zero() {
+			<PromiseArrears<T>>::mutate(promise_id, |arrears| *arrears -= compensation);
+		}
+	}
+
+	/// Check that `payer` can transfer `amount` and then pay `fee` before anything is written,
+	/// the storage isn't rolled back if a later step of the call fails.
+	fn ensure_can_pay(payer: &T::AccountId, amount: T::Balance, fee: T::Balance) -> Result {
+		let free = <balances::Module<T> as Currency<T::AccountId>>::free_balance(payer);
+		let transfer_fees = <balances::Module<T>>::transfer_fee() + <balances::Module<T>>::creation_fee();
+		let total = amount.checked_add(&fee)
+			.and_then(|total| total.checked_add(&transfer_fees))
+			.ok_or("Overflow adding the fees")?;
+		ensure!(free >= total, "Not enough free balance");
+		// the fee is withdrawn without killing the account:
+		if !fee.is_zero() {
+			ensure!(free - total >= <balances::Module<T> as Currency<T::AccountId>>::minimum_balance(),
+			        "Not enough free balance to pay the fee");
+		}
+		<balances::Module<T> as Currency<T::AccountId>>::ensure_can_withdraw(payer, total, WithdrawReason::Transfer, free - total)
+	}
+
+	/// Withdraw the protocol fee from `payer` and pass it to `T::OnFee`.
+	fn charge_fee(payer: &T::AccountId, c2fc_id: T::Hash, fee: T::Balance) -> Result {
+		if fee.is_zero() {
+			return Ok(());
+		}
+
+		let imbalance = <balances::Module<T> as Currency<T::AccountId>>::withdraw(
+			payer,
+			fee,
+			WithdrawReason::Fee,
+			ExistenceRequirement::KeepAlive,
+		)?;
+		T::OnFee::on_unbalanced(imbalance);
+
+		Self::deposit_event(RawEvent::FeeCharged(payer.clone(), c2fc_id, fee));
+
+		Ok(())
+	}
+
+
+	// queries of the runtime API //
+
+	pub fn bucket_info(c2fc_id: T::Hash) -> Option<BucketInfo<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		let owner = Self::owner_of_c2fc(c2fc_id)?;
+		let bucket = Self::c2fc(c2fc_id);
+		let arrears = bucket.promise.as_ref()
+			.map(|promise| Self::arrears_of_promise(promise.id))
+			.unwrap_or_else(Zero::zero);
+
+		Some(BucketInfo {
+			bucket,
+			owner,
+			contributor: Self::contributor_of_c2fc(c2fc_id),
+			arrears,
+			escrow: Self::escrow_of_c2fc(c2fc_id),
+		})
+	}
+
+	pub fn buckets_of_owner(owner: T::AccountId, offset: u64, limit: u64) -> Vec<BucketInfo<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		<OwnedBuckets<T>>::page(&owner, offset, limit)
+			.into_iter()
+			.filter_map(Self::bucket_info)
+			.collect()
+	}
+
+	/// Accepted promises the issuer owes payments on, as `(promise_id, c2fc_id)`.
+	pub fn promises_owed_by(issuer: T::AccountId, offset: u64, limit: u64) -> Vec<(T::Hash, T::Hash)> {
+		<IssuerPromises<T>>::page(&issuer, offset, limit)
+			.into_iter()
+			.map(|promise_id| (promise_id, Self::c2fc_by_promise(promise_id)))
+			.collect()
+	}
+
+	/// Buckets with a price set, i.e. listed for sale.
+	pub fn buckets_for_sale(offset: u64, limit: u64) -> Vec<BucketInfo<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		<AllBuckets<T>>::items()
+			.into_iter()
+			.filter(|c2fc_id| !Self::c2fc(c2fc_id).price.is_zero())
+			.filter_map(Self::bucket_info)
+			.skip(offset as usize)
+			.take(limit as usize)
+			.collect()
+	}
+
+	pub fn promise_info(promise_id: T::Hash) -> Option<PromiseInfo<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		let issuer = Self::owner_of_promise(promise_id)?;
+		let c2fc_id = if <AcceptedPromiseBucket<T>>::exists(promise_id) {
+			Some(Self::c2fc_by_promise(promise_id))
+		} else { None };
+		let stake = Self::stake_of_promise(&issuer, promise_id).unwrap_or_else(Zero::zero);
+
+		Some(PromiseInfo {
+			promise: Self::promise(promise_id),
+			issuer,
+			c2fc_id,
+			stake,
+		})
+	}
+
+	pub fn promises_of_owner(owner: T::AccountId, offset: u64, limit: u64) -> Vec<PromiseInfo<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		<OwnedPromises<T>>::page(&owner, offset, limit)
+			.into_iter()
+			.filter_map(Self::promise_info)
+			.collect()
+	}
+
+	/// Not accepted promises matching the filter, `offset` and `limit` apply to the matched ones.
+	pub fn free_promises(
+		filter: PromiseFilter<T::AccountId, T::Balance, T::BlockNumber>,
+		offset: u64,
+		limit: u64,
+	) -> Vec<PromiseInfo<T::Hash, T::Balance, T::AccountId, T::BlockNumber>> {
+		<FreePromises<T>>::items()
+			.into_iter()
+			.filter_map(Self::promise_info)
+			.filter(|info| {
+				let promise = &info.promise;
+				filter.issuer.as_ref().map_or(true, |issuer| issuer == &info.issuer) &&
+				filter.min_value.map_or(true, |min| promise.value >= min) &&
+				filter.max_value.map_or(true, |max| promise.value <= max) &&
+				filter.max_period.map_or(true, |max| promise.period <= max) &&
+				filter.revenue_share.map_or(true, |share| promise.revenue_share.is_some() == share) &&
+				filter.min_stake.map_or(true, |min| info.stake >= min)
+			})
+			.skip(offset as usize)
+			.take(limit as usize)
+			.collect()
+	}
+
+	/// Outstanding amount and the next deadline of accepted promise.
+	pub fn promise_schedule(promise_id: T::Hash) -> Option<PromiseSchedule<T::Hash, T::Balance, T::BlockNumber>> {
+		if !<AcceptedPromiseBucket<T>>::exists(promise_id) {
+			return None;
+		}
+		let c2fc_id = Self::c2fc_by_promise(promise_id);
+		let promise = Self::c2fc(c2fc_id).promise?;
+		if promise.period.is_zero() {
+			return None;
+		}
+
+		let period_index = Self::closed_periods(promise_id);
+		let due = Self::due_of_period(promise_id, &promise, period_index);
+
+		Some(PromiseSchedule {
+			promise_id,
+			c2fc_id,
+			due,
+			outstanding: if promise.filled < due { due - promise.filled } else { Zero::zero() },
+			arrears: Self::arrears_of_promise(promise_id),
+			next_deadline: promise.acception_dt + (period_index + One::one()) * promise.period,
+		})
+	}
+
+
+	// utilites //
+
+	/// Returns `(issuer, promise_id, stake)` for every accepted promise
+	/// which has a stake locked behind it and has no arrears.
+	pub fn collateral_in_good_standing() -> Vec<(T::AccountId, T::Hash, T::Balance)> {
+		<AcceptedPromises<T>>::items()
+			.into_iter()
+			.filter(|promise_id| Self::is_in_good_standing(*promise_id))
+			.filter_map(|promise_id| {
+				let issuer = Self::owner_of_promise(promise_id)?;
+				let stake = Self::stake_of_promise(&issuer, promise_id)?;
+				Some((issuer, promise_id, stake))
+			})
+			.filter(|(_, _, stake)| !stake.is_zero())
+			.collect()
+	}
+
+	/// Amount locked by `issuer` behind the specified promise.
+	pub fn stake_of_promise(issuer: &T::AccountId, promise_id: T::Hash) -> Option<T::Balance> {
+		if !<LockForPromise<T>>::exists(promise_id) {
+			return None;
+		}
+		get_lock::<T>(issuer, &Self::lock_for_promise(promise_id)).map(|lock| lock.amount)
+	}
+
+	#[inline]
+	pub fn is_in_good_standing(promise_id: T::Hash) -> bool {
+		Self::arrears_of_promise(promise_id).is_zero()
+	}
+
+	#[inline]
+	pub fn is_promise_accepted(promise_id: T::Hash) -> result::Result<bool, &'static str> {
+		ensure!(<Promises<T>>::exists(promise_id), "This promise does not exist");
+		Ok(<AcceptedPromiseBucket<T>>::exists(promise_id))
+	}
+}
+
+
+#[cfg(test)]
+mod fuzz;
+
+#[cfg(test)]
+mod bench;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::migration::{v0, MIGRATION_BATCH_SIZE, PHASE_BUCKETS};
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, assert_ok, assert_noop};
+	use runtime_primitives::{
+		BuildStorage,
+		traits::{BlakeTwo256, IdentityLookup, OnInitialize, OnFinalize, OffchainWorker},
+		testing::{Digest, DigestItem, Header}
+};
+	use std::cell::RefCell;
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	// For testing the module, we construct most of a mock runtime. This means
+	// first constructing a configuration type (`Test`) which `impl`s each of the
+	// configuration traits of modules we want to use.
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type Event = ();
+		type TransactionPayment = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+	}
+	thread_local! {
+		/// Calls submitted by the offchain worker.
+		static SUBMITTED: RefCell<Vec<Call<Test>>> = RefCell::new(Vec::new());
+	}
+
+	impl SubmitUnsigned<Call<Test>> for Test {
+		fn submit_unsigned(call: Call<Test>) -> result::Result<(), ()> {
+			SUBMITTED.with(|submitted| submitted.borrow_mut().push(call));
+			Ok(())
+		}
+	}
+
+	fn take_submitted() -> Vec<Call<Test>> {
+		SUBMITTED.with(|submitted| submitted.replace(Vec::new()))
+	}
+	/// Account collecting the protocol fees in place of the stake module of the runtime.
+	pub(super) const FEE_ACCOUNT: u64 = 100;
+
+	pub struct FeeAccount;
+	impl OnUnbalanced<NegativeImbalanceOf<Test>> for FeeAccount {
+		fn on_unbalanced(fee: NegativeImbalanceOf<Test>) {
+			let _ = Balances::deposit_creating(&FEE_ACCOUNT, fee.peek());
+		}
+	}
+
+	impl Trait for Test {
+		type SubmitTransaction = Test;
+		type Stake = balances::Module<Test>;
+		type OnFee = FeeAccount;
+		type Event = ();
+	}
+	pub(super) type CashflowModule = Module<Test>;
+	pub(super) type Balances = balances::Module<Test>;
+
+	// This function basically just builds a genesis storage key/value store according to
+	// our desired mockup.
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		new_test_ext_with_balances(vec![(1, 1000), (2, 1000), (3, 1000)])
+	}
+
+	pub(super) fn new_test_ext_with_balances(balances: Vec<(u64, u64)>) -> runtime_io::TestExternalities<Blake2Hasher> {
+		new_test_ext_with(balances, GenesisConfig::<Test>::default())
+	}
+
+	pub(super) fn new_test_ext_with(balances: Vec<(u64, u64)>, c2fc: GenesisConfig<Test>) -> runtime_io::TestExternalities<Blake2Hasher> {
+		new_test_ext_with_deposit(balances, 0, c2fc)
+	}
+
+	fn new_test_ext_with_deposit(balances: Vec<(u64, u64)>, existential_deposit: u64, c2fc: GenesisConfig<Test>)
+		-> runtime_io::TestExternalities<Blake2Hasher>
+	{
+		let (mut t, mut children) = system::GenesisConfig::<Test>::default().build_storage().unwrap();
+		balances::GenesisConfig::<Test> {
+			balances,
+			transaction_base_fee: 0,
+			transaction_byte_fee: 0,
+			existential_deposit,
+			transfer_fee: 0,
+			creation_fee: 0,
+			vesting: vec![],
+		}.assimilate_storage(&mut t, &mut children).unwrap();
+		// the endowment of the module accounts depends on the existential deposit:
+		c2fc.assimilate_storage(&mut t, &mut children).unwrap();
+		t.into()
+	}
+
+
+	// storage of the version 0 //
+
+	fn put_v0_bucket(owner: u64, c2fc_id: H256, promise: Option<v0::Promise<H256, u64, u64, u64>>) {
+		support::storage::put(&<Buckets<Test>>::key_for(c2fc_id), &v0::Bucket { id: c2fc_id, promise: promise.clone(), price: 0 });
+		<BucketOwner<Test>>::insert(c2fc_id, owner);
+		<AllBuckets<Test>>::insert(&c2fc_id).unwrap();
+		<OwnedBuckets<Test>>::insert(&owner, &c2fc_id).unwrap();
+
+		if let Some(promise) = promise {
+			<AcceptedPromiseBucket<Test>>::insert(promise.id, c2fc_id);
+			<BucketContributor<Test>>::insert(c2fc_id, promise.owner);
+			<AcceptedPromises<Test>>::insert(&promise.id).unwrap();
+		}
+	}
+
+	// accepted promises stayed in the free promises in the version 0:
+	fn put_v0_promise(issuer: u64, promise_id: H256, value: u64, period: u64) {
+		support::storage::put(&<Promises<Test>>::key_for(promise_id), &v0::FreePromise { id: promise_id, value, period, until: None });
+		<PromiseOwner<Test>>::insert(promise_id, issuer);
+		<FreePromises<Test>>::insert(&promise_id).unwrap();
+		<OwnedPromises<Test>>::insert(&issuer, &promise_id).unwrap();
+	}
+
+	fn v0_accepted(issuer: u64, promise_id: H256, value: u64, period: u64, acception_dt: u64) -> v0::Promise<H256, u64, u64, u64> {
+		v0::Promise { id: promise_id, owner: issuer, value, period, until: None, filled: 0, acception_dt }
+	}
+
+
+	#[test]
+	fn new_chain_starts_with_current_storage_version() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(CashflowModule::storage_version(), STORAGE_VERSION);
+			assert!(CashflowModule::is_storage_migrated());
+		});
+	}
+
+	#[test]
+	fn migration_reencodes_entries_of_version_0() {
+		with_externalities(&mut new_test_ext(), || {
+			<StorageVersion<Test>>::put(0);
+
+			let accepted_id = H256::repeat_byte(1);
+			let free_id = H256::repeat_byte(2);
+			let c2fc_id = H256::repeat_byte(3);
+			let empty_id = H256::repeat_byte(4);
+
+			put_v0_promise(2, accepted_id, 10, 5);
+			put_v0_promise(2, free_id, 20, 7);
+			put_v0_bucket(1, c2fc_id, Some(v0_accepted(2, accepted_id, 10, 5, 1)));
+			put_v0_bucket(1, empty_id, None);
+
+			system::Module::<Test>::set_block_number(12);
+			CashflowModule::on_initialize(12);
+
+			assert_eq!(CashflowModule::storage_version(), STORAGE_VERSION);
+
+			let promise = CashflowModule::c2fc(c2fc_id).promise.expect("promise is kept");
+			assert_eq!(promise.id, accepted_id);
+			assert_eq!(promise.owner, 2);
+			assert_eq!(promise.value, 10);
+			assert_eq!(promise.acception_dt, 1);
+			assert_eq!(promise.revenue_share, None);
+			assert_eq!(CashflowModule::c2fc(empty_id).promise, None);
+
+			assert_eq!(CashflowModule::promise(free_id).value, 20);
+			assert_eq!(CashflowModule::promise(free_id).revenue_share, None);
+			assert_eq!(CashflowModule::promise(accepted_id).period, 5);
+
+			// indexes of the new layout:
+			assert_eq!(<FreePromises<Test>>::items(), vec![free_id]);
+			assert_eq!(CashflowModule::promises_owed_by(2, 0, 10), vec![(accepted_id, c2fc_id)]);
+			// periods ended before the upgrade aren't closed again:
+			assert_eq!(CashflowModule::closed_periods(accepted_id), 2);
+			assert!(!CashflowModule::is_period_overdue(accepted_id, 12));
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn migration_resumes_in_next_block() {
+		with_externalities(&mut new_test_ext(), || {
+			<StorageVersion<Test>>::put(0);
+
+			let buckets = MIGRATION_BATCH_SIZE + 3;
+			for i in 0..buckets {
+				put_v0_bucket(1, H256::from_low_u64_be(i + 1), None);
+			}
+
+			CashflowModule::on_initialize(1);
+			assert!(!CashflowModule::is_storage_migrated());
+			assert_eq!(CashflowModule::migration_cursor(), (PHASE_BUCKETS, MIGRATION_BATCH_SIZE));
+			assert_noop!(CashflowModule::create_c2fc(Origin::signed(1)), "Storage migration is in progress");
+
+			CashflowModule::on_initialize(2);
+			assert!(CashflowModule::is_storage_migrated());
+			assert_eq!(CashflowModule::migration_cursor(), (0, 0));
+
+			for i in 0..buckets {
+				let c2fc_id = H256::from_low_u64_be(i + 1);
+				assert_eq!(CashflowModule::c2fc(c2fc_id).id, c2fc_id);
+			}
+			assert_ok!(CashflowModule::create_c2fc(Origin::signed(1)));
+			assert_eq!(CashflowModule::all_c2fc_count(), buckets + 1);
+		});
+	}
+
+	#[test]
+	fn migrated_storage_is_not_touched() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CashflowModule::create_c2fc(Origin::signed(1)));
+			let c2fc_id = CashflowModule::c2fc_by_index(0);
+			let bucket = CashflowModule::c2fc(c2fc_id);
+
+			CashflowModule::on_initialize(1);
+
+			assert_eq!(CashflowModule::c2fc(c2fc_id), bucket);
+			assert_eq!(CashflowModule::migration_cursor(), (0, 0));
+		});
+	}
+
+	#[test]
+	fn genesis_preloads_buckets_and_promises() {
+		let c2fc_id = H256::repeat_byte(1);
+		let sale_id = H256::repeat_byte(2);
+		let accepted_id = H256::repeat_byte(3);
+		let free_id = H256::repeat_byte(4);
+
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(GenesisConfig::<Test> {
+			oracles: vec![],
+			attestors: vec![],
+			buckets: vec![(1, c2fc_id, 0), (1, sale_id, 50)],
+			promises: vec![(2, accepted_id, 10, 5, 100), (3, free_id, 20, 7, 0)],
+			accepted_promises: vec![(accepted_id, c2fc_id)],
+			stakes: vec![(accepted_id, 30)],
+			parameters: C2fcParameters {
+				max_price: 100,
+				sale_fee: Permill::from_percent(2),
+				fill_fee: Permill::from_percent(1),
+				..Default::default()
+			},
+		}.build_storage().unwrap().0);
+
+		with_externalities(&mut t.into(), || {
+			assert_eq!(<AllBuckets<Test>>::items(), vec![c2fc_id, sale_id]);
+			assert_eq!(<OwnedBuckets<Test>>::items(&1), vec![c2fc_id, sale_id]);
+			assert_eq!(CashflowModule::c2fc(sale_id).price, 50);
+			assert_eq!(CashflowModule::buckets_for_sale(0, 10).len(), 1);
+
+			assert_eq!(<FreePromises<Test>>::items(), vec![free_id]);
+			assert_eq!(<OwnedPromises<Test>>::items(&2), vec![accepted_id]);
+			assert_eq!(CashflowModule::promise(free_id).until, None);
+
+			let promise = CashflowModule::c2fc(c2fc_id).promise.expect("promise is accepted");
+			assert_eq!(promise.owner, 2);
+			assert_eq!(promise.until, Some(100));
+			assert_eq!(promise.acception_dt, 0);
+			assert_eq!(CashflowModule::c2fc_by_promise(accepted_id), c2fc_id);
+			assert_eq!(CashflowModule::contributor_of_c2fc(c2fc_id), Some(2));
+			assert_eq!(CashflowModule::promises_owed_by(2, 0, 10), vec![(accepted_id, c2fc_id)]);
+			assert_eq!(<AcceptedPromises<Test>>::items(), vec![accepted_id]);
+
+			assert_eq!(CashflowModule::stake_of_promise(&2, accepted_id), Some(30));
+			assert_eq!(CashflowModule::collateral_in_good_standing(), vec![(2, accepted_id, 30)]);
+
+			assert_eq!(CashflowModule::parameters().sale_fee, Permill::from_percent(2));
+			assert_eq!(CashflowModule::parameters().fill_fee, Permill::from_percent(1));
+			assert_eq!(CashflowModule::parameters().max_price, 100);
+
+			assert_consistent();
+		});
+	}
+
+
+	// helpers //
+
+	fn new_c2fc(owner: u64) -> H256 {
+		assert_ok!(CashflowModule::create_c2fc(Origin::signed(owner)));
+		CashflowModule::c2fc_of_owner_by_index((owner, CashflowModule::owned_c2fc_count(owner) - 1))
+	}
+
+	fn new_promise(issuer: u64, value: u64, period: u64, until: u64) -> H256 {
+		assert_ok!(CashflowModule::create_promise_until(Origin::signed(issuer), value, period, until, false));
+		CashflowModule::promise_of_owner_by_index((issuer, CashflowModule::owned_promise_count(issuer) - 1))
+	}
+
+	/// Promise of the account 2 accepted into the c2fc of the account 1 at the current block.
+	fn accepted_promise(value: u64, period: u64, until: u64) -> (H256, H256) {
+		let c2fc_id = new_c2fc(1);
+		let promise_id = new_promise(2, value, period, until);
+		assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+		(c2fc_id, promise_id)
+	}
+
+	fn set_fees(sale_fee: Permill, fill_fee: Permill) -> support::dispatch::Result {
+		CashflowModule::set_parameters(Origin::ROOT, C2fcParameters { sale_fee, fill_fee, ..CashflowModule::parameters() })
+	}
+
+	fn assert_consistent() {
+		assert_eq!(CashflowModule::invariant_violations(), vec![]);
+	}
+
+	fn run_to_block(n: u64) {
+		let mut block = system::Module::<Test>::block_number();
+		while block < n {
+			block += 1;
+			system::Module::<Test>::set_block_number(block);
+			CashflowModule::on_finalize(block);
+		}
+	}
+
+
+	// creation //
+
+	#[test]
+	fn create_c2fc_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+			let another_id = new_c2fc(1);
+
+			assert_ne!(c2fc_id, another_id);
+			assert_eq!(CashflowModule::owner_of_c2fc(c2fc_id), Some(1));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).id, c2fc_id);
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise, None);
+			assert_eq!(CashflowModule::all_c2fc_count(), 2);
+			assert_eq!(<OwnedBuckets<Test>>::items(&1), vec![c2fc_id, another_id]);
+		});
+	}
+
+	#[test]
+	fn create_promise_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let promise_id = new_promise(2, 10, 5, 0);
+			let until_id = new_promise(2, 20, 7, 100);
+
+			assert_eq!(CashflowModule::owner_of_promise(promise_id), Some(2));
+			let promise = CashflowModule::promise(promise_id);
+			assert_eq!((promise.value, promise.period, promise.until), (10, 5, None));
+			assert_eq!(CashflowModule::promise(until_id).until, Some(100));
+			assert_eq!(<FreePromises<Test>>::items(), vec![promise_id, until_id]);
+			assert_eq!(<OwnedPromises<Test>>::items(&2), vec![promise_id, until_id]);
+
+			assert_ok!(CashflowModule::create_promise(Origin::signed(3), 30, 9));
+			assert_eq!(CashflowModule::free_promises_count(), 3);
+		});
+	}
+
+	#[test]
+	fn create_revenue_share_promise_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_percent(10), 5, 10, 0));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert_eq!(CashflowModule::promise(promise_id).revenue_share, Some(Permill::from_percent(10)));
+
+			assert_noop!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_millionths(0), 5, 10, 0),
+			             "Revenue share should be greater than zero");
+		});
+	}
+
+
+	// editing //
+
+	#[test]
+	fn edit_promise_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let promise_id = new_promise(2, 10, 5, 0);
+
+			assert_ok!(CashflowModule::edit_promise(Origin::signed(2), promise_id, 15, 6));
+			let promise = CashflowModule::promise(promise_id);
+			assert_eq!((promise.value, promise.period), (15, 6));
+		});
+	}
+
+	#[test]
+	fn edit_promise_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(CashflowModule::edit_promise(Origin::signed(2), H256::repeat_byte(9), 15, 6),
+			             "This promise does not exist");
+
+			let promise_id = new_promise(2, 10, 5, 0);
+			assert_noop!(CashflowModule::edit_promise(Origin::signed(3), promise_id, 15, 6),
+			             "You do not own this promise");
+
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+			assert_noop!(CashflowModule::edit_promise(Origin::signed(2), promise_id, 15, 6),
+			             "This promise already accepted so it cannot be changed");
+		});
+	}
+
+
+
+	// staking //
+
+	#[test]
+	fn stake_to_promise_locks_and_extends() {
+		with_externalities(&mut new_test_ext(), || {
+			let promise_id = new_promise(2, 10, 5, 0);
+
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 100));
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), Some(100));
+			assert_eq!(CashflowModule::locks_count(), 1);
+
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 50));
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), Some(150));
+			assert_eq!(CashflowModule::locks_count(), 1);
+		});
+	}
+
+	#[test]
+	fn stake_to_promise_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(CashflowModule::stake_to_promise(Origin::signed(2), H256::repeat_byte(9), 100),
+			             "This promise does not exist");
+
+			let promise_id = new_promise(2, 10, 5, 0);
+			assert_noop!(CashflowModule::stake_to_promise(Origin::signed(3), promise_id, 100),
+			             "You do not own this promise");
+		});
+	}
+
+	#[test]
+	fn withdraw_staken_works_after_the_lock() {
+		with_externalities(&mut new_test_ext(), || {
+			let promise_id = new_promise(2, 10, 5, 10);
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 100));
+
+			assert_noop!(CashflowModule::withdraw_staken(Origin::signed(2), promise_id),
+			             "This locked balance period isn't ended and stake cannot withdraw.");
+
+			run_to_block(10);
+			assert_ok!(CashflowModule::withdraw_staken(Origin::signed(2), promise_id));
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), None);
+			assert!(Balances::locks(&2).is_empty());
+
+			// a new stake gets a new lock:
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 10));
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), Some(10));
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn withdraw_staken_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(CashflowModule::withdraw_staken(Origin::signed(2), H256::repeat_byte(9)),
+			             "This promise does not exist");
+
+			let (_, promise_id) = accepted_promise(10, 5, 3);
+			assert_noop!(CashflowModule::withdraw_staken(Origin::signed(3), promise_id),
+			             "You do not own this promise");
+
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 100));
+			system::Module::<Test>::set_block_number(3);
+			assert_noop!(CashflowModule::withdraw_staken(Origin::signed(2), promise_id),
+			             "This promise already accepted so stake cannot withdraw.");
+		});
+	}
+
+
+	// acceptance //
+
+	#[test]
+	fn accept_promise_works() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(3);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+
+			let promise = CashflowModule::c2fc(c2fc_id).promise.expect("promise is accepted");
+			assert_eq!(promise.id, promise_id);
+			assert_eq!(promise.owner, 2);
+			assert_eq!(promise.acception_dt, 3);
+			assert_eq!(promise.filled, 0);
+
+			assert_eq!(CashflowModule::c2fc_by_promise(promise_id), c2fc_id);
+			assert_eq!(CashflowModule::contributor_of_c2fc(c2fc_id), Some(2));
+			assert_eq!(CashflowModule::is_promise_accepted(promise_id), Ok(true));
+			assert_eq!(<FreePromises<Test>>::items(), vec![]);
+			assert_eq!(<AcceptedPromises<Test>>::items(), vec![promise_id]);
+			assert_eq!(CashflowModule::promises_owed_by(2, 0, 10), vec![(promise_id, c2fc_id)]);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn accept_promise_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+			let promise_id = new_promise(2, 10, 5, 0);
+			let own_promise_id = new_promise(1, 10, 5, 0);
+
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), promise_id, H256::repeat_byte(9)),
+			             "This c2fc does not exist");
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), H256::repeat_byte(9), c2fc_id),
+			             "This promise does not exist");
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(3), promise_id, c2fc_id),
+			             "You do not own this c2fc");
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), own_promise_id, c2fc_id),
+			             "You can not accept your own promise");
+
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id),
+			             "This promise is already accepted");
+
+			let another_id = new_promise(3, 10, 5, 0);
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), another_id, c2fc_id),
+			             "Bucket already contains another promise");
+		});
+	}
+
+	#[test]
+	fn closed_promise_can_not_be_accepted_again() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 6);
+
+			run_to_block(6);
+			assert!(CashflowModule::is_promise_closed(promise_id));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise, None);
+			assert_eq!(<AcceptedPromises<Test>>::items(), vec![]);
+			assert_eq!(CashflowModule::promises_owed_by(2, 0, 10), vec![]);
+
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id),
+			             "This promise is closed");
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn closed_promise_leaves_no_items() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(CashflowModule::add_oracle(Origin::ROOT, 3));
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_percent(50), 10, 5, 6));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+			assert_ok!(CashflowModule::authorize_debit(Origin::signed(2), promise_id, 10));
+			assert_ok!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 20));
+
+			run_to_block(6);
+			assert!(CashflowModule::is_promise_closed(promise_id));
+			assert_eq!(Balances::free_balance(&1), 1010);
+			assert_eq!(CashflowModule::revenue_report((promise_id, 0)), None);
+			assert!(!<ClosedPeriods<Test>>::exists(promise_id));
+			assert!(!<DebitAuthorizations<Test>>::exists(promise_id));
+			assert!(!<PromiseEscrow<Test>>::exists(promise_id));
+			assert!(!<BucketEscrow<Test>>::exists(c2fc_id));
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn ended_promise_is_closed_after_the_escrow_is_released() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 6);
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 10));
+			assert_ok!(CashflowModule::deny_account(Origin::ROOT, 1));
+
+			// the escrow can't be paid to the denied owner, the promise stays accepted:
+			run_to_block(8);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 1);
+			assert!(!CashflowModule::is_promise_closed(promise_id));
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 10);
+
+			assert_ok!(CashflowModule::allow_account(Origin::ROOT, 1));
+			run_to_block(9);
+			assert!(CashflowModule::is_promise_closed(promise_id));
+			assert_eq!(Balances::free_balance(&1), 1010);
+			assert_eq!(CashflowModule::escrow_audit(), (0, 0));
+
+			assert_consistent();
+		});
+	}
+
+
+	// pricing, transfer & purchase //
+
+	#[test]
+	fn set_price_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).price, 50);
+
+			assert_noop!(CashflowModule::set_price(Origin::signed(1), H256::repeat_byte(9), 50),
+			             "This c2fc does not exist");
+			assert_noop!(CashflowModule::set_price(Origin::signed(2), c2fc_id, 50),
+			             "You do not own this c2fc");
+		});
+	}
+
+	#[test]
+	fn transfer_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+
+			assert_noop!(CashflowModule::transfer(Origin::signed(1), 2, H256::repeat_byte(9)),
+			             "No owner for this c2fc");
+			assert_noop!(CashflowModule::transfer(Origin::signed(2), 3, c2fc_id),
+			             "You do not own this c2fc");
+
+			assert_ok!(CashflowModule::transfer(Origin::signed(1), 2, c2fc_id));
+			assert_eq!(CashflowModule::owner_of_c2fc(c2fc_id), Some(2));
+			assert_eq!(CashflowModule::owned_c2fc_count(1), 0);
+			assert_eq!(<OwnedBuckets<Test>>::items(&2), vec![c2fc_id]);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn approved_account_can_transfer_and_list() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+
+			assert_noop!(CashflowModule::approve(Origin::signed(2), 2, c2fc_id), "You do not own this c2fc");
+			assert_noop!(CashflowModule::approve(Origin::signed(1), 1, c2fc_id), "You can't approve the owner of the c2fc");
+			assert_noop!(CashflowModule::clear_approval(Origin::signed(1), c2fc_id), "This c2fc has no approval");
+			assert_ok!(CashflowModule::approve(Origin::signed(1), 2, c2fc_id));
+			assert_eq!(CashflowModule::approved_for_c2fc(c2fc_id), Some(2));
+			// approved account can't approve others:
+			assert_noop!(CashflowModule::approve(Origin::signed(2), 3, c2fc_id), "You do not own this c2fc");
+
+			assert_ok!(CashflowModule::set_price(Origin::signed(2), c2fc_id, 50));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).price, 50);
+
+			assert_ok!(CashflowModule::transfer(Origin::signed(2), 3, c2fc_id));
+			assert_eq!(CashflowModule::owner_of_c2fc(c2fc_id), Some(3));
+			assert_eq!(CashflowModule::approved_for_c2fc(c2fc_id), None);
+			assert_noop!(CashflowModule::transfer(Origin::signed(2), 2, c2fc_id), "You do not own this c2fc");
+
+			assert_ok!(CashflowModule::approve(Origin::signed(3), 2, c2fc_id));
+			assert_ok!(CashflowModule::clear_approval(Origin::signed(3), c2fc_id));
+			assert_noop!(CashflowModule::set_price(Origin::signed(2), c2fc_id, 50), "You do not own this c2fc");
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn operator_manages_all_buckets_of_the_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+			let another_id = new_c2fc(1);
+
+			assert_noop!(CashflowModule::set_approval_for_all(Origin::signed(1), 1, true), "You can't approve yourself");
+			assert_ok!(CashflowModule::set_approval_for_all(Origin::signed(1), 2, true));
+			assert!(CashflowModule::is_approved_for_all((1, 2)));
+
+			// operator can approve others:
+			assert_ok!(CashflowModule::approve(Origin::signed(2), 3, c2fc_id));
+			assert_ok!(CashflowModule::transfer(Origin::signed(3), 3, c2fc_id));
+			assert_eq!(CashflowModule::owner_of_c2fc(c2fc_id), Some(3));
+
+			assert_ok!(CashflowModule::set_price(Origin::signed(2), another_id, 20));
+			assert_ok!(CashflowModule::buy_c2fc(Origin::signed(3), another_id, 20));
+			assert_eq!(CashflowModule::proceeds_of(1), 20);
+			// operator of the previous owner:
+			assert_noop!(CashflowModule::transfer(Origin::signed(2), 2, another_id), "You do not own this c2fc");
+
+			let last_id = new_c2fc(1);
+			assert_ok!(CashflowModule::set_approval_for_all(Origin::signed(1), 2, false));
+			assert!(!CashflowModule::is_approved_for_all((1, 2)));
+			assert_noop!(CashflowModule::transfer(Origin::signed(2), 2, last_id), "You do not own this c2fc");
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn payout_is_reset_on_transfer() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+
+			assert_noop!(CashflowModule::set_payout(Origin::signed(2), c2fc_id, 4), "You do not own this c2fc");
+			assert_noop!(CashflowModule::clear_payout(Origin::signed(1), c2fc_id), "This c2fc has no payout account");
+			assert_noop!(CashflowModule::clear_default_payout(Origin::signed(1)), "You have no default payout account");
+			assert_eq!(CashflowModule::payout_account(c2fc_id, &1), 1);
+
+			assert_ok!(CashflowModule::set_default_payout(Origin::signed(1), 5));
+			assert_eq!(CashflowModule::payout_account(c2fc_id, &1), 5);
+			assert_ok!(CashflowModule::set_payout(Origin::signed(1), c2fc_id, 4));
+			assert_eq!(CashflowModule::payout_account(c2fc_id, &1), 4);
+
+			// sale proceeds go to the payout of the seller:
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+			assert_ok!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 50));
+			assert_eq!(CashflowModule::proceeds_of(4), 50);
+			assert_ok!(CashflowModule::claim_proceeds(Origin::signed(4)));
+			assert_eq!(Balances::free_balance(&4), 50);
+			assert_eq!(Balances::free_balance(&1), 1000);
+
+			assert_eq!(CashflowModule::payout_of_c2fc(c2fc_id), None);
+			assert_eq!(CashflowModule::payout_account(c2fc_id, &3), 3);
+
+			assert_ok!(CashflowModule::clear_default_payout(Origin::signed(1)));
+			assert_eq!(CashflowModule::default_payout_of(1), None);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn escrow_and_compensation_go_to_the_payout() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 30));
+
+			assert_ok!(CashflowModule::set_payout(Origin::signed(1), c2fc_id, 4));
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			run_to_block(6);
+			assert_eq!(Balances::free_balance(&4), 10);
+
+			assert_ok!(CashflowModule::clear_payout(Origin::signed(1), c2fc_id));
+			assert_ok!(CashflowModule::set_default_payout(Origin::signed(1), 5));
+			run_to_block(11);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(Balances::free_balance(&5), 10);
+			assert_eq!(Balances::free_balance(&1), 1000);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn escrow_below_the_existential_deposit_is_kept_alive_by_the_endowment() {
+		let balances = vec![(1, 1000), (2, 1000), (3, 1000)];
+		with_externalities(&mut new_test_ext_with_deposit(balances, 20, GenesisConfig::<Test>::default()), || {
+			system::Module::<Test>::set_block_number(1);
+			let escrow = CashflowModule::escrow_account();
+			assert_eq!(Balances::total_balance(&escrow), 20);
+			assert_eq!(Balances::total_balance(&CashflowModule::insurance_account()), 20);
+			assert_eq!(CashflowModule::escrow_audit(), (0, 0));
+
+			// the hold and the release are below the existential deposit,
+			// the payout can't receive it and the owner is paid:
+			let (c2fc_id, _) = accepted_promise(10, 5, 0);
+			assert_ok!(CashflowModule::set_payout(Origin::signed(1), c2fc_id, 4));
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			assert_eq!(CashflowModule::escrow_audit(), (10, 10));
+			run_to_block(6);
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 0);
+			assert_eq!(Balances::free_balance(&4), 0);
+			assert_eq!(Balances::free_balance(&1), 1010);
+
+			// claim of all proceeds leaves the endowment:
+			let other_id = new_c2fc(3);
+			assert_ok!(CashflowModule::set_price(Origin::signed(3), other_id, 10));
+			assert_ok!(CashflowModule::buy_c2fc(Origin::signed(2), other_id, 10));
+			assert_ok!(CashflowModule::claim_proceeds(Origin::signed(3)));
+			assert_eq!(Balances::free_balance(&3), 1010);
+			assert_eq!(Balances::total_balance(&escrow), 20);
+			assert_eq!(CashflowModule::escrow_audit(), (0, 0));
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn buy_c2fc_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+
+			assert_ok!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 60));
+
+			assert_eq!(CashflowModule::owner_of_c2fc(c2fc_id), Some(3));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).price, 0);
+			assert_eq!(Balances::free_balance(&3), 950);
+
+			// the proceeds are held in escrow until claimed:
+			assert_eq!(CashflowModule::proceeds_of(1), 50);
+			assert_eq!(CashflowModule::escrow_audit(), (50, 50));
+			assert_noop!(CashflowModule::claim_proceeds(Origin::signed(3)), "No proceeds to claim");
+			assert_ok!(CashflowModule::claim_proceeds(Origin::signed(1)));
+			assert_eq!(Balances::free_balance(&1), 1050);
+			assert_eq!(CashflowModule::escrow_audit(), (0, 0));
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn buy_c2fc_charges_the_sale_fee() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(set_fees(Permill::from_percent(10), Permill::from_millionths(0)));
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+
+			assert_ok!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 50));
+			assert_ok!(CashflowModule::claim_proceeds(Origin::signed(1)));
+
+			assert_eq!(Balances::free_balance(&1), 1045);
+			assert_eq!(Balances::free_balance(&3), 950);
+			assert_eq!(Balances::free_balance(&FEE_ACCOUNT), 5);
+		});
+	}
+
+	#[test]
+	fn buy_c2fc_charges_nothing_if_the_buyer_cant_pay() {
+		with_externalities(&mut new_test_ext_with_balances(vec![(1, 1000), (3, 40)]), || {
+			assert_ok!(set_fees(Permill::from_percent(10), Permill::from_millionths(0)));
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 50), "Not enough free balance");
+			assert_eq!(Balances::free_balance(&3), 40);
+			assert_eq!(CashflowModule::owner_of_c2fc(c2fc_id), Some(1));
+		});
+	}
+
+	#[test]
+	fn fill_c2fc_charges_the_fee_on_top_of_the_deposit() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(set_fees(Permill::from_millionths(0), Permill::from_percent(10)));
+			let (c2fc_id, _) = accepted_promise(10, 5, 0);
+
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 10));
+			assert_eq!(Balances::free_balance(&2), 989);
+			assert_eq!(Balances::free_balance(&FEE_ACCOUNT), 1);
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 10);
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise.unwrap().filled, 10);
+
+			run_to_block(6);
+			assert_eq!(Balances::free_balance(&1), 1010);
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn set_parameters_works() {
+		with_externalities(&mut new_test_ext(), || {
+			let parameters = C2fcParameters {
+				min_period: 5,
+				max_period: 50,
+				max_value: 100,
+				max_price: 200,
+				max_promises_per_account: 2,
+				report_grace: 0,
+				sale_fee: Permill::from_percent(1),
+				fill_fee: Permill::from_percent(1),
+			};
+
+			assert!(CashflowModule::set_parameters(Origin::signed(1), parameters.clone()).is_err());
+			assert_noop!(CashflowModule::set_parameters(Origin::ROOT, C2fcParameters { min_period: 0, ..parameters.clone() }),
+			             "Min period should be greater than zero");
+			assert_noop!(CashflowModule::set_parameters(Origin::ROOT, C2fcParameters { max_period: 4, ..parameters.clone() }),
+			             "Min period should not exceed max period");
+
+			assert_ok!(CashflowModule::set_parameters(Origin::ROOT, parameters.clone()));
+			assert_eq!(CashflowModule::parameters(), parameters);
+		});
+	}
+
+	#[test]
+	fn parameters_limit_promises_and_prices() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+			let promise_id = new_promise(2, 10, 5, 0);
+			assert_ok!(CashflowModule::set_parameters(Origin::ROOT, C2fcParameters {
+				min_period: 5,
+				max_period: 50,
+				max_value: 100,
+				max_price: 200,
+				max_promises_per_account: 2,
+				..CashflowModule::parameters()
+			}));
+
+			assert_noop!(CashflowModule::create_promise_until(Origin::signed(2), 10, 4, 0, false), "Promise period is shorter than the min period");
+			assert_noop!(CashflowModule::create_promise_until(Origin::signed(2), 10, 51, 0, false), "Promise period is longer than the max period");
+			assert_noop!(CashflowModule::create_promise_until(Origin::signed(2), 101, 5, 0, false), "Promise value exceeds the max value");
+			new_promise(2, 100, 50, 0);
+			assert_noop!(CashflowModule::create_promise_until(Origin::signed(2), 10, 5, 0, false), "Too many promises of the account");
+
+			assert_noop!(CashflowModule::edit_promise(Origin::signed(2), promise_id, 101, 5), "Promise value exceeds the max value");
+			assert_noop!(CashflowModule::edit_promise(Origin::signed(2), promise_id, 10, 51), "Promise period is longer than the max period");
+			assert_ok!(CashflowModule::edit_promise(Origin::signed(2), promise_id, 20, 10));
+
+			assert_noop!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 201), "Price exceeds the max price");
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 200));
+
+			// promise created under the previous parameters:
+			assert_ok!(CashflowModule::set_parameters(Origin::ROOT, C2fcParameters {
+				max_value: 15,
+				..CashflowModule::parameters()
+			}));
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id), "Promise value exceeds the max value");
+		});
+	}
+
+	#[test]
+	fn closed_promises_dont_count_toward_the_limit() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CashflowModule::set_parameters(Origin::ROOT, C2fcParameters {
+				max_promises_per_account: 1,
+				..CashflowModule::parameters()
+			}));
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, _) = accepted_promise(10, 5, 6);
+			assert_noop!(CashflowModule::create_promise_until(Origin::signed(2), 10, 5, 0, false), "Too many promises of the account");
+
+			// the only period of the promise is closed:
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 10));
+			run_to_block(6);
+			assert_eq!(CashflowModule::open_promise_count(&2), 0);
+			assert_ok!(CashflowModule::create_promise_until(Origin::signed(2), 10, 5, 0, false));
+		});
+	}
+
+	#[test]
+	fn pause_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert!(CashflowModule::pause(Origin::signed(1), PauseCategory::Trading).is_err());
+			assert_noop!(CashflowModule::unpause(Origin::ROOT, PauseCategory::Trading), "This category is not paused");
+
+			assert_ok!(CashflowModule::pause(Origin::ROOT, PauseCategory::Trading));
+			assert!(CashflowModule::is_category_paused(PauseCategory::Trading));
+			assert!(!CashflowModule::is_category_paused(PauseCategory::Creation));
+			assert_noop!(CashflowModule::pause(Origin::ROOT, PauseCategory::Trading), "This category is already paused");
+
+			assert!(CashflowModule::unpause(Origin::signed(1), PauseCategory::Trading).is_err());
+			assert_ok!(CashflowModule::unpause(Origin::ROOT, PauseCategory::Trading));
+			assert!(!CashflowModule::is_category_paused(PauseCategory::Trading));
+		});
+	}
+
+	#[test]
+	fn paused_calls_are_rejected() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::pause(Origin::ROOT, PauseCategory::Trading));
+
+			assert_noop!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50), "This call is paused");
+			assert_noop!(CashflowModule::transfer(Origin::signed(1), 2, c2fc_id), "This call is paused");
+			assert!(CashflowModule::is_call_paused(&Call::buy_c2fc(c2fc_id, 50)));
+			assert!(!CashflowModule::is_call_paused(&Call::create_c2fc()));
+			new_c2fc(1);
+
+			assert_ok!(CashflowModule::pause(Origin::ROOT, PauseCategory::All));
+			assert_noop!(CashflowModule::create_c2fc(Origin::signed(1)), "This call is paused");
+			assert_noop!(CashflowModule::create_promise(Origin::signed(2), 10, 5), "This call is paused");
+			assert!(CashflowModule::is_call_paused(&Call::fill_c2fc(c2fc_id, 10)));
+			// root calls are never paused:
+			assert!(!CashflowModule::is_call_paused(&Call::set_parameters(CashflowModule::parameters())));
+			assert_ok!(set_fees(Permill::from_percent(1), Permill::from_percent(1)));
+		});
+	}
+
+	#[test]
+	fn denied_account_can_not_trade() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+			let promise_id = new_promise(2, 10, 5, 0);
+			assert!(CashflowModule::deny_account(Origin::signed(1), 3).is_err());
+			assert_ok!(CashflowModule::deny_account(Origin::ROOT, 3));
+			assert_noop!(CashflowModule::deny_account(Origin::ROOT, 3), "This account is already on the deny list");
+
+			assert_noop!(CashflowModule::create_c2fc(Origin::signed(3)), "This account is on the deny list");
+			assert_noop!(CashflowModule::create_promise_until(Origin::signed(3), 10, 5, 0, false), "This account is on the deny list");
+			assert_noop!(CashflowModule::stake_to_promise(Origin::signed(3), promise_id, 10), "This account is on the deny list");
+			assert_noop!(CashflowModule::transfer(Origin::signed(1), 3, c2fc_id), "The counterparty is on the deny list");
+
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 50), "This account is on the deny list");
+
+			// sale from the listed account:
+			assert_ok!(CashflowModule::allow_account(Origin::ROOT, 3));
+			assert_noop!(CashflowModule::allow_account(Origin::ROOT, 3), "This account is not on the deny list");
+			assert_ok!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 50));
+			assert_ok!(CashflowModule::set_price(Origin::signed(3), c2fc_id, 50));
+			assert_ok!(CashflowModule::deny_account(Origin::ROOT, 3));
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(1), c2fc_id, 50), "The counterparty is on the deny list");
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(3), promise_id, c2fc_id), "This account is on the deny list");
+		});
+	}
+
+	#[test]
+	fn denied_issuer_can_fill_existing_obligations() {
+		with_externalities(&mut new_test_ext(), || {
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+			let free_id = new_promise(2, 10, 5, 0);
+			let empty_id = new_c2fc(1);
+			assert_ok!(CashflowModule::deny_account(Origin::ROOT, 2));
+
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), free_id, empty_id), "The counterparty is on the deny list");
+			assert_noop!(CashflowModule::withdraw_staken(Origin::signed(2), promise_id), "This account is on the deny list");
+
+			assert_ok!(CashflowModule::authorize_debit(Origin::signed(2), promise_id, 10));
+			assert_noop!(CashflowModule::authorize_debit(Origin::signed(2), free_id, 10), "This account is on the deny list");
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 5));
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise.unwrap().filled, 10);
+			// the same check rejects the transaction before the inclusion:
+			assert_ok!(CashflowModule::ensure_call_allowed(&2, &Call::fullfill_c2fc(c2fc_id)));
+			let other_id = new_c2fc(3);
+			assert_eq!(CashflowModule::ensure_call_allowed(&2, &Call::fullfill_c2fc(other_id)), Err("This account is on the deny list"));
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn listed_payout_and_owner_are_not_paid() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+
+			// the listed payout is skipped:
+			assert_ok!(CashflowModule::set_payout(Origin::signed(1), c2fc_id, 4));
+			assert_ok!(CashflowModule::deny_account(Origin::ROOT, 4));
+			assert_eq!(CashflowModule::payout_account(c2fc_id, &1), 1);
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			run_to_block(6);
+			assert_eq!(Balances::free_balance(&4), 0);
+			assert_eq!(Balances::free_balance(&1), 1010);
+
+			// the escrow of the listed owner is kept in custody:
+			assert_ok!(CashflowModule::deny_account(Origin::ROOT, 1));
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			run_to_block(11);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 2);
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 10);
+			assert_eq!(Balances::free_balance(&1), 1010);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn buy_c2fc_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			let c2fc_id = new_c2fc(1);
+
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(3), H256::repeat_byte(9), 60),
+			             "This c2fc does not exist");
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(1), c2fc_id, 60),
+			             "You can't buy your own c2fc");
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 60),
+			             "The c2fc you want to buy is not for sale");
+
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 40),
+			             "The c2fc you want to buy costs more than your max price");
+		});
+	}
+
+	#[test]
+	fn fill_c2fc_holds_the_deposit_until_the_end_of_period() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 4));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise.unwrap().filled, 4);
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 4);
+			assert_eq!(CashflowModule::escrow_audit(), (4, 4));
+
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise.unwrap().filled, 10);
+			assert_eq!(Balances::free_balance(&2), 990);
+
+			run_to_block(6);
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 0);
+			assert_eq!(CashflowModule::escrow_audit(), (0, 0));
+			assert_eq!(Balances::free_balance(&1), 1010);
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise.unwrap().filled, 0);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 1);
+			assert!(CashflowModule::is_in_good_standing(promise_id));
+		});
+	}
+
+	#[test]
+	fn fill_c2fc_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let c2fc_id = new_c2fc(1);
+
+			assert_noop!(CashflowModule::fill_c2fc(Origin::signed(2), H256::repeat_byte(9), 10),
+			             "This c2fc does not exist");
+			assert_noop!(CashflowModule::fill_c2fc(Origin::signed(1), c2fc_id, 10),
+			             "You can't fill your own c2fc");
+			assert_noop!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 10),
+			             "This c2fc does not contains promise");
+
+			let promise_id = new_promise(2, 10, 5, 0);
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 10));
+			assert_noop!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 10),
+			             "The c2fc you want to fill is already fullfilled");
+			assert_noop!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id),
+			             "The c2fc you want to fill is already fullfilled");
+
+			let (zero_id, _) = accepted_promise(0, 5, 0);
+			assert_noop!(CashflowModule::fill_c2fc(Origin::signed(2), zero_id, 10),
+			             "The promise in the c2fc you want to fill is invalid");
+			let empty_id = new_c2fc(1);
+			assert_noop!(CashflowModule::fullfill_c2fc(Origin::signed(2), empty_id),
+			             "This c2fc doesnt contains an accepted promise");
+		});
+	}
+
+
+	// breach detection //
+
+	#[test]
+	fn on_finalize_registers_the_breach() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 4));
+
+			run_to_block(5);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+
+			run_to_block(6);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 6);
+			assert!(!CashflowModule::is_in_good_standing(promise_id));
+			// the partial deposit is paid anyway:
+			assert_eq!(Balances::free_balance(&1), 1004);
+
+			// the arrears are repaid first:
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 8));
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise.unwrap().filled, 2);
+
+			run_to_block(11);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 8);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn breach_is_compensated_from_the_stake() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 30));
+
+			run_to_block(6);
+
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), Some(20));
+			assert_eq!(Balances::free_balance(&1), 1010);
+			assert_eq!(Balances::free_balance(&2), 990);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn breach_is_not_slashed_while_filling_is_paused() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 30));
+			assert_ok!(CashflowModule::authorize_debit(Origin::signed(2), promise_id, 100));
+			assert_ok!(CashflowModule::pause(Origin::ROOT, PauseCategory::Filling));
+
+			run_to_block(6);
+
+			// neither debited nor slashed:
+			assert_eq!(CashflowModule::closed_periods(promise_id), 1);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 10);
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), Some(30));
+			assert_eq!(Balances::free_balance(&1), 1000);
+			assert_eq!(Balances::free_balance(&2), 1000);
+
+			assert_ok!(CashflowModule::unpause(Origin::ROOT, PauseCategory::Filling));
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 10));
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn authorized_debit_fills_the_promise() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+
+			assert_noop!(CashflowModule::authorize_debit(Origin::signed(3), promise_id, 100),
+			             "You do not own this promise");
+			assert_noop!(CashflowModule::authorize_debit(Origin::signed(2), promise_id, 0),
+			             "Debit limit should be greater than zero");
+			assert_noop!(CashflowModule::revoke_debit(Origin::signed(2), promise_id),
+			             "This promise has no debit authorization");
+
+			assert_ok!(CashflowModule::authorize_debit(Origin::signed(2), promise_id, 100));
+			run_to_block(6);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(Balances::free_balance(&1), 1010);
+
+			assert_ok!(CashflowModule::revoke_debit(Origin::signed(2), promise_id));
+			run_to_block(11);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 10);
+		});
+	}
+
+	#[test]
+	fn authorized_debit_leaves_the_stake_and_charges_the_fee_after_the_hold() {
+		with_externalities(&mut new_test_ext_with_balances(vec![(1, 1000), (2, 105)]), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(set_fees(Permill::from_millionths(0), Permill::from_percent(10)));
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 100));
+			assert_ok!(CashflowModule::authorize_debit(Origin::signed(2), promise_id, 100));
+
+			// only 5 above the stake, nothing is debited or charged:
+			run_to_block(6);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(Balances::free_balance(&1), 1010);
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), Some(90));
+			assert_eq!(Balances::free_balance(&2), 95);
+		});
+	}
+
+	#[test]
+	fn close_overdue_period_fails_without_overdue_periods() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+
+			assert_noop!(CashflowModule::close_overdue_period(Origin::INHERENT, promise_id),
+			             "This promise has no overdue periods");
+			assert!(CashflowModule::close_overdue_period(Origin::signed(1), promise_id).is_err());
+		});
+	}
+
+	#[test]
+	fn offchain_worker_submits_the_overdue_period() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+			run_to_block(5);
+			take_submitted();
+
+			// the period isn't over yet:
+			CashflowModule::generate_extrinsics(5);
+			assert_eq!(take_submitted(), vec![]);
+
+			// the period ended at the block 6, but it isn't closed yet:
+			CashflowModule::generate_extrinsics(6);
+			assert_eq!(take_submitted(), vec![Call::close_overdue_period(promise_id)]);
+
+			run_to_block(6);
+			CashflowModule::generate_extrinsics(6);
+			assert_eq!(take_submitted(), vec![]);
+		});
+	}
+
+	#[test]
+	fn validate_unsigned_accepts_only_overdue_periods() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+			let call = Call::close_overdue_period(promise_id);
+
+			run_to_block(4);
+			assert_eq!(CashflowModule::validate_unsigned(&call), TransactionValidity::Invalid(INVALID_UNSIGNED_CALL));
+
+			// the period ends at the next block:
+			run_to_block(5);
+			let valid = TransactionValidity::Valid {
+				priority: 0,
+				requires: vec![],
+				provides: vec![(promise_id, 0u64).encode()],
+				longevity: TransactionLongevity::max_value(),
+			};
+			assert_eq!(CashflowModule::validate_unsigned(&call), valid);
+			// the duplicate provides the same tag, the pool keeps one transaction per period:
+			assert_eq!(CashflowModule::validate_unsigned(&call), valid);
+
+			// the period is closed, the duplicate is stale:
+			run_to_block(6);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 1);
+			assert_eq!(CashflowModule::validate_unsigned(&call), TransactionValidity::Invalid(INVALID_UNSIGNED_CALL));
+
+			// the next period gets another tag:
+			run_to_block(10);
+			assert_eq!(CashflowModule::validate_unsigned(&call), TransactionValidity::Valid {
+				priority: 0,
+				requires: vec![],
+				provides: vec![(promise_id, 1u64).encode()],
+				longevity: TransactionLongevity::max_value(),
+			});
+
+			assert_eq!(CashflowModule::validate_unsigned(&Call::create_c2fc()), TransactionValidity::Invalid(INVALID_UNSIGNED_CALL));
+		});
+	}
+
+
+	// KYC attestations //
+
+	#[test]
+	fn attestation_works() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let doc_hash = H256::repeat_byte(7);
+
+			assert_noop!(CashflowModule::attest(Origin::signed(3), 1, doc_hash, 10), "Only root or attestors can attest accounts");
+			assert!(CashflowModule::add_attestor(Origin::signed(1), 3).is_err());
+			assert_ok!(CashflowModule::add_attestor(Origin::ROOT, 3));
+			assert_noop!(CashflowModule::add_attestor(Origin::ROOT, 3), "This account is already an attestor");
+
+			assert_noop!(CashflowModule::attest(Origin::signed(3), 1, doc_hash, 1), "Attestation should expire in the future");
+			assert_ok!(CashflowModule::attest(Origin::signed(3), 1, doc_hash, 10));
+			assert_ok!(CashflowModule::attest(Origin::ROOT, 2, doc_hash, 10));
+			assert_eq!(CashflowModule::attestation_of(1), Some(Attestation { attestor: Some(3), doc_hash, expires_at: 10 }));
+			assert_eq!(CashflowModule::attestation_of(2).unwrap().attestor, None);
+			assert!(CashflowModule::is_verified(&1));
+
+			system::Module::<Test>::set_block_number(10);
+			assert!(!CashflowModule::is_verified(&1));
+
+			assert_ok!(CashflowModule::remove_attestor(Origin::ROOT, 3));
+			assert_noop!(CashflowModule::revoke_attestation(Origin::signed(3), 2), "Only root or attestors can attest accounts");
+			assert_ok!(CashflowModule::revoke_attestation(Origin::ROOT, 2));
+			assert_noop!(CashflowModule::revoke_attestation(Origin::ROOT, 2), "This account is not attested");
+			assert_eq!(CashflowModule::attestation_of(2), None);
+		});
+	}
+
+	#[test]
+	fn verified_only_promise_requires_verified_counterparties() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			let doc_hash = H256::repeat_byte(7);
+			let c2fc_id = new_c2fc(1);
+
+			assert_noop!(CashflowModule::create_promise_until(Origin::signed(2), 10, 5, 0, true),
+			             "Only verified accounts can issue this promise");
+			assert_ok!(CashflowModule::attest(Origin::ROOT, 2, doc_hash, 100));
+			assert_ok!(CashflowModule::create_promise_until(Origin::signed(2), 10, 5, 0, true));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert!(CashflowModule::promise(promise_id).verified_only);
+
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id),
+			             "Only verified accounts can accept this promise");
+			assert_ok!(CashflowModule::attest(Origin::ROOT, 1, doc_hash, 100));
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+
+			assert_ok!(CashflowModule::set_price(Origin::signed(1), c2fc_id, 50));
+			assert_noop!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 50), "Only verified accounts can own this c2fc");
+			assert_ok!(CashflowModule::attest(Origin::ROOT, 3, doc_hash, 100));
+			assert_ok!(CashflowModule::buy_c2fc(Origin::signed(3), c2fc_id, 50));
+
+			// neither transfer nor the operator moves the c2fc to unverified account:
+			assert_noop!(CashflowModule::transfer(Origin::signed(3), 4, c2fc_id), "Only verified accounts can own this c2fc");
+			assert_ok!(CashflowModule::set_approval_for_all(Origin::signed(3), 5, true));
+			assert_noop!(CashflowModule::transfer(Origin::signed(5), 4, c2fc_id), "Only verified accounts can own this c2fc");
+			assert_ok!(CashflowModule::attest(Origin::ROOT, 4, doc_hash, 100));
+			assert_ok!(CashflowModule::transfer(Origin::signed(5), 4, c2fc_id));
+			assert_eq!(CashflowModule::owner_of_c2fc(c2fc_id), Some(4));
+
+			// promises without the flag are open for everybody:
+			let open_id = new_promise(2, 10, 5, 0);
+			assert!(!CashflowModule::promise(open_id).verified_only);
+
+			assert_consistent();
+		});
+	}
+
+
+	// revenue oracles //
+
+	#[test]
+	fn reported_revenue_changes_the_due() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert!(CashflowModule::add_oracle(Origin::signed(1), 3).is_err());
+			assert_ok!(CashflowModule::add_oracle(Origin::ROOT, 3));
+			assert_noop!(CashflowModule::add_oracle(Origin::ROOT, 3), "This account is already an oracle");
+
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_percent(50), 10, 5, 0));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+
+			assert_noop!(CashflowModule::report_revenue(Origin::signed(2), promise_id, 0, 100),
+			             "Only oracles can report the revenue");
+			assert_noop!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 1, 100),
+			             "This period hasn't started yet");
+			assert_ok!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 100));
+			assert_noop!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 100),
+			             "The revenue for this period is already reported");
+			assert_eq!(CashflowModule::promise_schedule(promise_id).unwrap().due, 50);
+
+			assert_noop!(CashflowModule::dispute_revenue(Origin::signed(3), promise_id, 0),
+			             "Only issuer or owner of the c2fc can dispute the revenue");
+			assert_noop!(CashflowModule::resolve_dispute(Origin::ROOT, promise_id, 0, 40),
+			             "The revenue for this period is not disputed");
+			assert_ok!(CashflowModule::dispute_revenue(Origin::signed(2), promise_id, 0));
+			assert_noop!(CashflowModule::dispute_revenue(Origin::signed(1), promise_id, 0),
+			             "The revenue for this period is already disputed");
+			assert_eq!(CashflowModule::promise_schedule(promise_id).unwrap().due, 10);
+
+			assert_ok!(CashflowModule::resolve_dispute(Origin::ROOT, promise_id, 0, 40));
+			assert_eq!(CashflowModule::promise_schedule(promise_id).unwrap().due, 20);
+
+			assert_ok!(CashflowModule::remove_oracle(Origin::ROOT, 3));
+			assert_noop!(CashflowModule::remove_oracle(Origin::ROOT, 3), "This account is not an oracle");
+		});
+	}
+
+	#[test]
+	fn revenue_of_fixed_promise_can_not_be_reported() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(CashflowModule::add_oracle(Origin::ROOT, 3));
+
+			assert_noop!(CashflowModule::report_revenue(Origin::signed(3), H256::repeat_byte(9), 0, 100),
+			             "This promise is not accepted");
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+			assert_noop!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 100),
+			             "This promise does not share the revenue");
+			assert_noop!(CashflowModule::dispute_revenue(Origin::signed(2), promise_id, 0),
+			             "The revenue for this period is not reported");
+		});
+	}
+
+	#[test]
+	fn ended_period_is_reported_during_the_grace() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(CashflowModule::set_parameters(Origin::ROOT, C2fcParameters {
+				report_grace: 3,
+				..CashflowModule::parameters()
+			}));
+			assert_ok!(CashflowModule::add_oracle(Origin::ROOT, 3));
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_percent(50), 10, 5, 0));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 30));
+
+			// the period has ended but is open for the report:
+			run_to_block(7);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 0);
+			assert_ok!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 60));
+
+			run_to_block(9);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 1);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(Balances::free_balance(&1), 1030);
+			assert_noop!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 60),
+			             "This period is already closed");
+			assert_noop!(CashflowModule::dispute_revenue(Origin::signed(1), promise_id, 0),
+			             "This period is already closed");
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn fullfill_pays_the_reported_share_and_the_arrears() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(CashflowModule::add_oracle(Origin::ROOT, 3));
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_percent(50), 10, 5, 0));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+
+			assert_ok!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 60));
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 30);
+			assert_noop!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id),
+			             "The c2fc you want to fill is already fullfilled");
+
+			// the second period is breached, the fixed value goes to the arrears:
+			run_to_block(11);
+			assert_eq!(Balances::free_balance(&1), 1030);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 10);
+
+			assert_ok!(CashflowModule::fullfill_c2fc(Origin::signed(2), c2fc_id));
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(CashflowModule::c2fc(c2fc_id).promise.unwrap().filled, 10);
+			assert_eq!(Balances::free_balance(&2), 950);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn every_period_is_closed_when_the_grace_is_longer_than_the_period() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(CashflowModule::set_parameters(Origin::ROOT, C2fcParameters {
+				report_grace: 7,
+				..CashflowModule::parameters()
+			}));
+			let c2fc_id = new_c2fc(1);
+			// four periods ending at the blocks 6, 11, 16 and 21:
+			assert_ok!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_percent(50), 10, 5, 21));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+			assert_ok!(CashflowModule::authorize_debit(Origin::signed(2), promise_id, 10));
+
+			// the time is over but two periods are open:
+			run_to_block(23);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 3);
+			assert!(!CashflowModule::is_promise_closed(promise_id));
+
+			run_to_block(30);
+			assert!(CashflowModule::is_promise_closed(promise_id));
+			assert_eq!(Balances::free_balance(&1), 1040);
+			assert_eq!(Balances::free_balance(&2), 960);
+
+			assert_consistent();
+		});
+	}
+
+	#[test]
+	fn disputed_escrow_is_released_after_resolution() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(CashflowModule::add_oracle(Origin::ROOT, 3));
+			let c2fc_id = new_c2fc(1);
+			assert_ok!(CashflowModule::create_revenue_share_promise(Origin::signed(2), Permill::from_percent(50), 10, 5, 0));
+			let promise_id = CashflowModule::promise_of_owner_by_index((2, 0));
+			assert_ok!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+
+			assert_ok!(CashflowModule::report_revenue(Origin::signed(3), promise_id, 0, 100));
+			assert_ok!(CashflowModule::fill_c2fc(Origin::signed(2), c2fc_id, 50));
+			assert_ok!(CashflowModule::dispute_revenue(Origin::signed(2), promise_id, 0));
+
+			// the disputed period isn't closed, the escrow is held back:
+			run_to_block(6);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 0);
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 50);
+			assert_eq!(Balances::free_balance(&1), 1000);
+
+			assert_ok!(CashflowModule::resolve_dispute(Origin::ROOT, promise_id, 0, 100));
+			assert_eq!(CashflowModule::open_disputes(promise_id), 0);
+			run_to_block(7);
+			assert_eq!(CashflowModule::closed_periods(promise_id), 1);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(CashflowModule::escrow_of_c2fc(c2fc_id), 0);
+			assert_eq!(Balances::free_balance(&1), 1050);
+			assert_eq!(CashflowModule::escrow_audit(), (0, 0));
+
+			assert_consistent();
+		});
+	}
+
+
+	// insurance //
+
+	#[test]
+	fn insurance_premium_funds_the_pool() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert!(CashflowModule::set_insurance(Origin::signed(1), true, Permill::from_percent(10), Permill::from_percent(0), 100).is_err());
+			assert_ok!(CashflowModule::set_insurance(Origin::ROOT, true, Permill::from_percent(10), Permill::from_percent(0), 100));
+
+			let (c2fc_id, promise_id) = accepted_promise(50, 5, 0);
+			assert_eq!(CashflowModule::insurance_pool(), 5);
+			assert_eq!(CashflowModule::insurance_audit(), (5, 5));
+			assert_eq!(CashflowModule::premium_of_promise(promise_id), 5);
+			assert_eq!(Balances::free_balance(&2), 995);
+
+			// the pool covers the breach:
+			run_to_block(6);
+			assert_eq!(CashflowModule::claims_of_c2fc(c2fc_id), 5);
+			assert_eq!(CashflowModule::insurance_pool(), 0);
+			assert_eq!(CashflowModule::insurance_audit(), (0, 0));
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 45);
+			assert_eq!(Balances::free_balance(&1), 1005);
+		});
+	}
+
+	#[test]
+	fn premium_is_not_paid_if_the_promise_can_not_be_attached() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CashflowModule::set_insurance(Origin::ROOT, true, Permill::from_percent(10), Permill::from_percent(0), 100));
+			let c2fc_id = new_c2fc(1);
+			let promise_id = new_promise(2, 50, 5, 0);
+			// broken index of the free promises:
+			assert_ok!(<FreePromises<Test>>::remove(&promise_id));
+
+			assert_noop!(CashflowModule::accept_promise(Origin::signed(1), promise_id, c2fc_id),
+			             "This promise is not indexed as free");
+			assert_eq!(CashflowModule::insurance_pool(), 0);
+			assert_eq!(CashflowModule::premium_of_promise(promise_id), 0);
+			assert_eq!(Balances::free_balance(&2), 1000);
+		});
+	}
+
+	#[test]
+	fn insured_breach_slashes_the_shortfall_and_the_contribution() {
+		with_externalities(&mut new_test_ext(), || {
+			system::Module::<Test>::set_block_number(1);
+			assert_ok!(CashflowModule::set_insurance(Origin::ROOT, true, Permill::from_percent(0), Permill::from_percent(50), 100));
+			let (_, promise_id) = accepted_promise(10, 5, 0);
+			assert_ok!(CashflowModule::stake_to_promise(Origin::signed(2), promise_id, 100));
+
+			run_to_block(6);
+
+			// the shortfall of 10 and 50% of it for the pool, the rest of the stake stays locked:
+			assert_eq!(CashflowModule::stake_of_promise(&2, promise_id), Some(85));
+			assert_eq!(Balances::free_balance(&2), 985);
+			assert_eq!(Balances::free_balance(&1), 1010);
+			assert_eq!(CashflowModule::arrears_of_promise(promise_id), 0);
+			assert_eq!(CashflowModule::insurance_audit(), (5, 5));
+		});
+	}
+
+
+	// invariants //
+
+	#[test]
+	fn invariant_violations_are_reported_with_identifiers() {
+		with_externalities(&mut new_test_ext(), || {
+			let (c2fc_id, promise_id) = accepted_promise(10, 5, 0);
+			let free_id = new_promise(3, 10, 5, 0);
+			assert_consistent();
+
+			// broken by hand:
+			<BucketContributor<Test>>::insert(c2fc_id, 3);
+			<BucketOwner<Test>>::insert(c2fc_id, 4);
+			<LockForPromise<Test>>::insert(free_id, *b"c2fc/bad");
+			<FreePromisesIndex<Test>>::insert(free_id, 7);
+
+			let violations = CashflowModule::invariant_violations();
+			assert!(violations.contains(&Violation::ContributorMismatch(c2fc_id)));
+			assert!(violations.contains(&Violation::NotIndexed(Collection::OwnedBuckets, c2fc_id)));
+			assert!(violations.contains(&Violation::MissingLock(free_id, 3)));
+			assert!(violations.contains(&Violation::IndexCorrupted(Collection::FreePromises, 0, free_id)));
+			assert!(!violations.iter().any(|violation| *violation == Violation::AcceptedPromiseIsFree(promise_id)));
+		});
 	}
 
 	#[test]
-	fn it_works_for_default_value() {
+	fn check_invariants_is_root_only() {
 		with_externalities(&mut new_test_ext(), || {
-			// Just a dummy test for the dummy funtion `do_something`
-			// calling the `do_something` function with a value 42
-			assert_ok!(CashflowModule::do_something(Origin::signed(1), 42));
-			// asserting that the stored value is equal to what we stored
-			assert_eq!(CashflowModule::something(), Some(42));
+			assert!(CashflowModule::check_invariants(Origin::signed(1)).is_err());
+			assert_ok!(CashflowModule::check_invariants(Origin::ROOT));
 		});
 	}
 }
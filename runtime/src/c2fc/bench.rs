@@ -0,0 +1,230 @@
+//! Benchmarks of the calls and of the block finalization at several sizes of the storage.
+//!
+//! Run with `cargo test -p akropolis-runtime --release -- --ignored benchmark_c2fc`,
+//! the report is written to `$C2FC_BENCH_REPORT` (`target/c2fc-bench.json` by default).
+
+use std::cell::Cell;
+use std::time::Instant;
+
+use primitives::{H256, Blake2Hasher, offchain};
+use runtime_io::{with_externalities, Externalities, ChildStorageKey, TestExternalities};
+use runtime_primitives::traits::{BlakeTwo256, OnFinalize};
+use serde_json::json;
+
+use super::*;
+use super::tests::{Origin, Test, CashflowModule, new_test_ext_with};
+
+/// Numbers of accepted promises (and buckets of a single owner) in the seeded storage.
+const SIZES: &[u64] = &[100, 1_000, 10_000];
+
+/// Calls measured at each size, each one on another bucket or promise.
+const ITERATIONS: u64 = 20;
+
+/// Period of the seeded promises, all of them end at this block.
+const PERIOD: u64 = 10;
+
+const OWNER: u64 = 1;
+const ISSUER: u64 = 2;
+const FREE_ISSUER: u64 = 3;
+const BUYER: u64 = 4;
+const NEWCOMER: u64 = 5;
+
+/// Kinds of the seeded ids.
+const SEEDED_BUCKET: u8 = 0;
+const SEEDED_PROMISE: u8 = 1;
+const FREE_PROMISE: u8 = 2;
+const EMPTY_BUCKET: u8 = 3;
+
+fn id(kind: u8, i: u64) -> H256 {
+	BlakeTwo256::hash_of(&(kind, i))
+}
+
+/// Externalities counting the storage reads and writes.
+struct CountingExternalities<'a> {
+	inner: &'a mut TestExternalities<Blake2Hasher>,
+	reads: Cell<u64>,
+	writes: u64,
+}
+
+impl<'a> CountingExternalities<'a> {
+	fn new(inner: &'a mut TestExternalities<Blake2Hasher>) -> Self {
+		CountingExternalities { inner, reads: Cell::new(0), writes: 0 }
+	}
+
+	fn read(&self) {
+		self.reads.set(self.reads.get() + 1);
+	}
+}
+
+impl<'a> Externalities<Blake2Hasher> for CountingExternalities<'a> {
+	fn storage(&self, key: &[u8]) -> Option<Vec<u8>> {
+		self.read();
+		self.inner.storage(key)
+	}
+
+	fn original_storage(&self, key: &[u8]) -> Option<Vec<u8>> {
+		self.read();
+		self.inner.original_storage(key)
+	}
+
+	fn child_storage(&self, storage_key: ChildStorageKey<Blake2Hasher>, key: &[u8]) -> Option<Vec<u8>> {
+		self.read();
+		self.inner.child_storage(storage_key, key)
+	}
+
+	fn kill_child_storage(&mut self, storage_key: ChildStorageKey<Blake2Hasher>) {
+		self.writes += 1;
+		self.inner.kill_child_storage(storage_key)
+	}
+
+	fn clear_prefix(&mut self, prefix: &[u8]) {
+		self.writes += 1;
+		self.inner.clear_prefix(prefix)
+	}
+
+	fn place_storage(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) {
+		self.writes += 1;
+		self.inner.place_storage(key, value)
+	}
+
+	fn place_child_storage(&mut self, storage_key: ChildStorageKey<Blake2Hasher>, key: Vec<u8>, value: Option<Vec<u8>>) {
+		self.writes += 1;
+		self.inner.place_child_storage(storage_key, key, value)
+	}
+
+	fn chain_id(&self) -> u64 {
+		self.inner.chain_id()
+	}
+
+	fn storage_root(&mut self) -> H256 {
+		self.inner.storage_root()
+	}
+
+	fn child_storage_root(&mut self, storage_key: ChildStorageKey<Blake2Hasher>) -> Vec<u8> {
+		self.inner.child_storage_root(storage_key)
+	}
+
+	fn storage_changes_root(&mut self, parent: H256, parent_num: u64) -> Option<H256> {
+		self.inner.storage_changes_root(parent, parent_num)
+	}
+
+	fn offchain(&mut self) -> Option<&mut dyn offchain::Externalities> {
+		self.inner.offchain()
+	}
+}
+
+/// Storage with `size` promises of `ISSUER` accepted into the buckets of `OWNER`,
+/// and free promises and empty buckets for the acceptance.
+fn seeded_ext(size: u64) -> TestExternalities<Blake2Hasher> {
+	let balances = vec![
+		(OWNER, 1_000_000), (ISSUER, 1_000_000), (FREE_ISSUER, 1_000_000), (BUYER, 1_000_000), (NEWCOMER, 1_000_000),
+	];
+
+	let mut config = GenesisConfig::<Test>::default();
+	for i in 0..size {
+		config.buckets.push((OWNER, id(SEEDED_BUCKET, i), 1));
+		config.promises.push((ISSUER, id(SEEDED_PROMISE, i), 10, PERIOD, 0));
+		config.accepted_promises.push((id(SEEDED_PROMISE, i), id(SEEDED_BUCKET, i)));
+	}
+	for i in 0..ITERATIONS {
+		config.buckets.push((BUYER, id(EMPTY_BUCKET, i), 0));
+		config.promises.push((FREE_ISSUER, id(FREE_PROMISE, i), 10, PERIOD, 0));
+	}
+
+	new_test_ext_with(balances, config)
+}
+
+/// Run `call` for `0..iterations` and return the JSON record of the averages.
+fn measure<F>(ext: &mut TestExternalities<Blake2Hasher>, name: &str, size: u64, iterations: u64, mut call: F) -> serde_json::Value
+	where F: FnMut(u64) -> Result
+{
+	let mut counting = CountingExternalities::new(ext);
+
+	let started = Instant::now();
+	with_externalities(&mut counting, || {
+		for i in 0..iterations {
+			if let Err(e) = call(i) {
+				panic!("{} failed at size {}: {}", name, size, e);
+			}
+		}
+	});
+	let elapsed = started.elapsed();
+	let nanos = elapsed.as_secs() as u128 * 1_000_000_000 + elapsed.subsec_nanos() as u128;
+
+	json!({
+		"name": name,
+		"size": size,
+		"iterations": iterations,
+		"mean_ns": (nanos / iterations as u128) as u64,
+		"reads_per_call": counting.reads.get() / iterations,
+		"writes_per_call": counting.writes / iterations,
+	})
+}
+
+fn benchmark_size(size: u64) -> Vec<serde_json::Value> {
+	let mut ext = seeded_ext(size);
+	with_externalities(&mut ext, || <system::Module<Test>>::set_block_number(1));
+
+	let mut results = Vec::new();
+	let mut bench = |name: &str, iterations: u64, call: &mut dyn FnMut(u64) -> Result| {
+		results.push(measure(&mut ext, name, size, iterations, call));
+	};
+
+	bench("create_c2fc", ITERATIONS, &mut |_| CashflowModule::create_c2fc(Origin::signed(NEWCOMER)));
+	bench("create_promise_until", ITERATIONS, &mut |_| {
+		CashflowModule::create_promise_until(Origin::signed(NEWCOMER), 10, PERIOD, 0, false)
+	});
+	bench("stake_to_promise", ITERATIONS, &mut |i| {
+		CashflowModule::stake_to_promise(Origin::signed(FREE_ISSUER), id(FREE_PROMISE, i), 10)
+	});
+	bench("edit_promise", ITERATIONS, &mut |i| {
+		CashflowModule::edit_promise(Origin::signed(FREE_ISSUER), id(FREE_PROMISE, i), 20, PERIOD)
+	});
+	bench("accept_promise", ITERATIONS, &mut |i| {
+		CashflowModule::accept_promise(Origin::signed(BUYER), id(FREE_PROMISE, i), id(EMPTY_BUCKET, i))
+	});
+	bench("set_price", ITERATIONS, &mut |i| {
+		CashflowModule::set_price(Origin::signed(OWNER), id(SEEDED_BUCKET, i), 5)
+	});
+	// the owner holds `size` buckets:
+	bench("transfer", ITERATIONS, &mut |i| {
+		CashflowModule::transfer(Origin::signed(OWNER), NEWCOMER, id(SEEDED_BUCKET, i))
+	});
+	bench("buy_c2fc", ITERATIONS, &mut |i| {
+		CashflowModule::buy_c2fc(Origin::signed(BUYER), id(SEEDED_BUCKET, i), 5)
+	});
+	bench("fill_c2fc", ITERATIONS, &mut |i| {
+		CashflowModule::fill_c2fc(Origin::signed(ISSUER), id(SEEDED_BUCKET, ITERATIONS + i), 5)
+	});
+
+	// finalization of a block without ended periods and of the block where all of them end:
+	bench("on_finalize_idle", 1, &mut |_| {
+		<system::Module<Test>>::set_block_number(PERIOD - 1);
+		CashflowModule::on_finalize(PERIOD - 1);
+		Ok(())
+	});
+	bench("on_finalize_periods_end", 1, &mut |_| {
+		<system::Module<Test>>::set_block_number(PERIOD);
+		CashflowModule::on_finalize(PERIOD);
+		Ok(())
+	});
+
+	results
+}
+
+#[test]
+#[ignore]
+fn benchmark_c2fc() {
+	let results: Vec<_> = SIZES.iter().flat_map(|size| benchmark_size(*size)).collect();
+
+	let report = json!({
+		"sizes": SIZES,
+		"iterations": ITERATIONS,
+		"results": results,
+	});
+
+	let path = std::env::var("C2FC_BENCH_REPORT").unwrap_or_else(|_| "target/c2fc-bench.json".into());
+	std::fs::write(&path, serde_json::to_string_pretty(&report).expect("report is serializable"))
+		.expect("report should be written");
+	println!("C2FC benchmark report: {}", path);
+}
@@ -0,0 +1,230 @@
+//! Property-based tests running random sequences of calls against the mock runtime.
+//!
+//! After each step the storage should be consistent and no funds should appear or vanish.
+//! A failing sequence is shrunk by proptest to a minimal reproduction.
+
+use proptest::prelude::*;
+use proptest::collection::vec;
+use proptest::sample::Index;
+
+use primitives::H256;
+use runtime_io::with_externalities;
+use runtime_primitives::traits::{OnInitialize, OnFinalize};
+use support::traits::Currency;
+
+use super::*;
+use super::tests::{Origin, Test, CashflowModule, Balances, FEE_ACCOUNT, new_test_ext_with_balances};
+
+const ACCOUNTS: u64 = 5;
+const ENDOWMENT: u64 = 1_000;
+
+/// Call of the module. Buckets and promises are referred to by the index in the list
+/// of the created ones, so the same sequence is valid for any generated ids.
+#[derive(Clone, Debug)]
+enum Action {
+	CreateC2fc(u64),
+	/// (issuer, value, period, until)
+	CreatePromise(u64, u64, u64, u64),
+	/// (issuer, share_percent, value, period, until)
+	CreateRevenueSharePromise(u64, u32, u64, u64, u64),
+	EditPromise(u64, Index, u64, u64),
+	Stake(u64, Index, u64),
+	Withdraw(u64, Index),
+	AuthorizeDebit(u64, Index, u64),
+	Accept(u64, Index, Index),
+	SetPrice(u64, Index, u64),
+	Transfer(u64, u64, Index),
+	Buy(u64, Index, u64),
+	ClaimProceeds(u64),
+	Fill(u64, Index, u64),
+	Fullfill(u64, Index),
+	/// (enabled, premium_percent, contribution_percent, coverage)
+	SetInsurance(bool, u32, u32, u64),
+	/// (sale_fee_percent, fill_fee_percent)
+	SetFees(u32, u32),
+	AddOracle(u64),
+	/// (oracle, promise, period_index, revenue)
+	ReportRevenue(u64, Index, u64, u64),
+	DisputeRevenue(u64, Index, u64),
+	ResolveDispute(Index, u64, u64),
+	SetReportGrace(u64),
+	/// Finalize a few blocks.
+	Tick(u64),
+}
+
+fn action() -> impl Strategy<Value = Action> {
+	let who = 1..=ACCOUNTS;
+	let amount = 0..200u64;
+	prop_oneof![
+		who.clone().prop_map(Action::CreateC2fc),
+		(who.clone(), amount.clone(), 0..8u64, 0..40u64)
+			.prop_map(|(who, value, period, until)| Action::CreatePromise(who, value, period, until)),
+		(who.clone(), any::<Index>(), amount.clone(), 0..8u64)
+			.prop_map(|(who, promise, value, period)| Action::EditPromise(who, promise, value, period)),
+		(who.clone(), any::<Index>(), amount.clone()).prop_map(|(who, promise, amount)| Action::Stake(who, promise, amount)),
+		(who.clone(), any::<Index>()).prop_map(|(who, promise)| Action::Withdraw(who, promise)),
+		(who.clone(), any::<Index>(), amount.clone()).prop_map(|(who, promise, limit)| Action::AuthorizeDebit(who, promise, limit)),
+		(who.clone(), any::<Index>(), any::<Index>()).prop_map(|(who, promise, c2fc)| Action::Accept(who, promise, c2fc)),
+		(who.clone(), any::<Index>(), amount.clone()).prop_map(|(who, c2fc, price)| Action::SetPrice(who, c2fc, price)),
+		(who.clone(), who.clone(), any::<Index>()).prop_map(|(who, to, c2fc)| Action::Transfer(who, to, c2fc)),
+		(who.clone(), any::<Index>(), amount.clone()).prop_map(|(who, c2fc, max)| Action::Buy(who, c2fc, max)),
+		who.clone().prop_map(Action::ClaimProceeds),
+		(who.clone(), any::<Index>(), amount.clone()).prop_map(|(who, c2fc, deposit)| Action::Fill(who, c2fc, deposit)),
+		(who.clone(), any::<Index>()).prop_map(|(who, c2fc)| Action::Fullfill(who, c2fc)),
+		(any::<bool>(), 0..30u32, 0..100u32, amount.clone())
+			.prop_map(|(enabled, premium, contribution, coverage)| Action::SetInsurance(enabled, premium, contribution, coverage)),
+		(who.clone(), 0..=100u32, amount.clone(), 0..8u64, 0..40u64)
+			.prop_map(|(who, share, value, period, until)| Action::CreateRevenueSharePromise(who, share, value, period, until)),
+		(0..20u32, 0..20u32).prop_map(|(sale, fill)| Action::SetFees(sale, fill)),
+		who.clone().prop_map(Action::AddOracle),
+		(who.clone(), any::<Index>(), 0..4u64, 0..400u64)
+			.prop_map(|(who, promise, period, revenue)| Action::ReportRevenue(who, promise, period, revenue)),
+		(who.clone(), any::<Index>(), 0..4u64).prop_map(|(who, promise, period)| Action::DisputeRevenue(who, promise, period)),
+		(any::<Index>(), 0..4u64, 0..400u64).prop_map(|(promise, period, revenue)| Action::ResolveDispute(promise, period, revenue)),
+		(0..5u64).prop_map(Action::SetReportGrace),
+		(1..10u64).prop_map(Action::Tick),
+	]
+}
+
+/// Ids created by the sequence.
+#[derive(Default)]
+struct Created {
+	buckets: Vec<H256>,
+	promises: Vec<H256>,
+}
+
+impl Created {
+	fn bucket(&self, index: &Index) -> H256 {
+		if self.buckets.is_empty() { H256::zero() } else { self.buckets[index.index(self.buckets.len())] }
+	}
+
+	fn promise(&self, index: &Index) -> H256 {
+		if self.promises.is_empty() { H256::zero() } else { self.promises[index.index(self.promises.len())] }
+	}
+}
+
+/// Funds of all accounts, held in escrow, in the insurance pool and collected as fees.
+fn total_funds() -> u64 {
+	let accounts: u64 = (1..=ACCOUNTS).map(|who| Balances::total_balance(&who)).sum();
+	accounts +
+		Balances::total_balance(&CashflowModule::escrow_account()) +
+		Balances::total_balance(&CashflowModule::insurance_account()) +
+		Balances::total_balance(&FEE_ACCOUNT)
+}
+
+fn apply(action: &Action, created: &mut Created) {
+	// failed calls are fine, they just shouldn't break anything:
+	let _ = match *action {
+		Action::CreateC2fc(who) => {
+			let result = CashflowModule::create_c2fc(Origin::signed(who));
+			if result.is_ok() {
+				let count = CashflowModule::owned_c2fc_count(who);
+				created.buckets.push(CashflowModule::c2fc_of_owner_by_index((who, count - 1)));
+			}
+			result
+		},
+		Action::CreatePromise(who, value, period, until) => {
+			let result = CashflowModule::create_promise_until(Origin::signed(who), value, period, until, false);
+			if result.is_ok() {
+				let count = CashflowModule::owned_promise_count(who);
+				created.promises.push(CashflowModule::promise_of_owner_by_index((who, count - 1)));
+			}
+			result
+		},
+		Action::CreateRevenueSharePromise(who, share, value, period, until) => {
+			let result = CashflowModule::create_revenue_share_promise(Origin::signed(who), Permill::from_percent(share),
+			                                                          value, period, until);
+			if result.is_ok() {
+				let count = CashflowModule::owned_promise_count(who);
+				created.promises.push(CashflowModule::promise_of_owner_by_index((who, count - 1)));
+			}
+			result
+		},
+		Action::EditPromise(who, ref promise, value, period) =>
+			CashflowModule::edit_promise(Origin::signed(who), created.promise(promise), value, period),
+		Action::Stake(who, ref promise, amount) =>
+			CashflowModule::stake_to_promise(Origin::signed(who), created.promise(promise), amount),
+		Action::Withdraw(who, ref promise) =>
+			CashflowModule::withdraw_staken(Origin::signed(who), created.promise(promise)),
+		Action::AuthorizeDebit(who, ref promise, limit) =>
+			CashflowModule::authorize_debit(Origin::signed(who), created.promise(promise), limit),
+		Action::Accept(who, ref promise, ref c2fc) =>
+			CashflowModule::accept_promise(Origin::signed(who), created.promise(promise), created.bucket(c2fc)),
+		Action::SetPrice(who, ref c2fc, price) =>
+			CashflowModule::set_price(Origin::signed(who), created.bucket(c2fc), price),
+		Action::Transfer(who, to, ref c2fc) =>
+			CashflowModule::transfer(Origin::signed(who), to, created.bucket(c2fc)),
+		Action::Buy(who, ref c2fc, max_price) =>
+			CashflowModule::buy_c2fc(Origin::signed(who), created.bucket(c2fc), max_price),
+		Action::ClaimProceeds(who) =>
+			CashflowModule::claim_proceeds(Origin::signed(who)),
+		Action::Fill(who, ref c2fc, deposit) =>
+			CashflowModule::fill_c2fc(Origin::signed(who), created.bucket(c2fc), deposit),
+		Action::Fullfill(who, ref c2fc) =>
+			CashflowModule::fullfill_c2fc(Origin::signed(who), created.bucket(c2fc)),
+		Action::SetInsurance(enabled, premium, contribution, coverage) =>
+			CashflowModule::set_insurance(Origin::ROOT, enabled, Permill::from_percent(premium),
+			                              Permill::from_percent(contribution), coverage),
+		Action::SetFees(sale, fill) =>
+			CashflowModule::set_parameters(Origin::ROOT, C2fcParameters {
+				sale_fee: Permill::from_percent(sale),
+				fill_fee: Permill::from_percent(fill),
+				..CashflowModule::parameters()
+			}),
+		Action::AddOracle(who) =>
+			CashflowModule::add_oracle(Origin::ROOT, who),
+		Action::ReportRevenue(who, ref promise, period, revenue) =>
+			CashflowModule::report_revenue(Origin::signed(who), created.promise(promise), period, revenue),
+		Action::DisputeRevenue(who, ref promise, period) =>
+			CashflowModule::dispute_revenue(Origin::signed(who), created.promise(promise), period),
+		Action::ResolveDispute(ref promise, period, revenue) =>
+			CashflowModule::resolve_dispute(Origin::ROOT, created.promise(promise), period, revenue),
+		Action::SetReportGrace(report_grace) =>
+			CashflowModule::set_parameters(Origin::ROOT, C2fcParameters { report_grace, ..CashflowModule::parameters() }),
+		Action::Tick(blocks) => {
+			let now = <system::Module<Test>>::block_number();
+			for block in now + 1..=now + blocks {
+				<system::Module<Test>>::set_block_number(block);
+				CashflowModule::on_initialize(block);
+				CashflowModule::on_finalize(block);
+			}
+			Ok(())
+		},
+	};
+}
+
+fn run(actions: &[Action]) -> Result<(), TestCaseError> {
+	let balances = (1..=ACCOUNTS).map(|who| (who, ENDOWMENT)).collect();
+
+	with_externalities(&mut new_test_ext_with_balances(balances), || {
+		<system::Module<Test>>::set_block_number(1);
+		let funds = total_funds();
+		let mut created = Created::default();
+
+		for (step, action) in actions.iter().enumerate() {
+			apply(action, &mut created);
+
+			prop_assert_eq!(CashflowModule::invariant_violations(), vec![], "step {}: {:?}", step, action);
+			let (tracked, actual) = CashflowModule::escrow_audit();
+			prop_assert_eq!(tracked, actual, "escrow at step {}: {:?}", step, action);
+			let (tracked, actual) = CashflowModule::insurance_audit();
+			prop_assert_eq!(tracked, actual, "insurance pool at step {}: {:?}", step, action);
+			prop_assert_eq!(total_funds(), funds, "funds at step {}: {:?}", step, action);
+		}
+
+		Ok(())
+	})
+}
+
+proptest! {
+	#![proptest_config(ProptestConfig {
+		cases: 256,
+		max_shrink_iters: 10_000,
+		.. ProptestConfig::default()
+	})]
+
+	#[test]
+	fn random_calls_keep_storage_consistent_and_funds_conserved(actions in vec(action(), 1..80)) {
+		run(&actions)?;
+	}
+}
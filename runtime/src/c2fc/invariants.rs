@@ -0,0 +1,216 @@
+//! Consistency checks of the module storage.
+//!
+//! Per-account indexes can't be enumerated by account, so they are checked
+//! for the accounts found through the global indexes.
+
+use rstd::collections::btree_set::BTreeSet;
+use parity_codec::Codec;
+
+use super::*;
+
+/// Indexes of buckets and promises.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Collection {
+	AllBuckets,
+	OwnedBuckets,
+	FreePromises,
+	OwnedPromises,
+	AcceptedPromises,
+	IssuerPromises,
+}
+
+/// Broken invariant with the identifiers of the involved entries.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Violation<Hash, AccountId> {
+	/// Item at the position of the index doesn't point back to that position.
+	/// (collection, position, item)
+	IndexCorrupted(Collection, u64, Hash),
+	/// Item of the index isn't stored.
+	DanglingIndexItem(Collection, Hash),
+	/// Item in the index of the account is owned by somebody else.
+	/// (collection, account, item)
+	WrongIndexOwner(Collection, AccountId, Hash),
+	/// Bucket or promise is missing in the index.
+	NotIndexed(Collection, Hash),
+	/// Bucket or promise has no owner.
+	MissingOwner(Hash),
+	/// Bucket stored under another id.
+	BucketIdMismatch(Hash),
+	/// `AcceptedPromiseBucket` disagrees with the promise of the bucket.
+	/// (promise_id, c2fc_id)
+	AcceptedPromiseMismatch(Hash, Hash),
+	/// `BucketContributor` isn't the issuer of the accepted promise.
+	ContributorMismatch(Hash),
+	/// Accepted promise is still free.
+	AcceptedPromiseIsFree(Hash),
+	/// `LockForPromise` points to a lock the issuer doesn't have.
+	/// (promise_id, issuer)
+	MissingLock(Hash, AccountId),
+}
+
+pub type ViolationOf<T> = Violation<<T as system::Trait>::Hash, <T as system::Trait>::AccountId>;
+
+
+impl<T: Trait> Module<T> {
+	/// All broken invariants of the storage, empty if the storage is consistent.
+	pub fn invariant_violations() -> Vec<ViolationOf<T>> {
+		let mut violations = Vec::new();
+
+		let mut owners = BTreeSet::new();
+		let mut issuers = BTreeSet::new();
+		let mut promise_owners = BTreeSet::new();
+
+		// buckets:
+		for position in 0..<AllBuckets<T>>::count() {
+			let c2fc_id = match <AllBuckets<T>>::at(position) {
+				Some(c2fc_id) => c2fc_id,
+				None => continue,
+			};
+			if <AllBuckets<T>>::index_of(&c2fc_id) != Some(position) {
+				violations.push(Violation::IndexCorrupted(Collection::AllBuckets, position, c2fc_id));
+			}
+			if !<Buckets<T>>::exists(c2fc_id) {
+				violations.push(Violation::DanglingIndexItem(Collection::AllBuckets, c2fc_id));
+				continue;
+			}
+
+			let bucket = Self::c2fc(c2fc_id);
+			if bucket.id != c2fc_id {
+				violations.push(Violation::BucketIdMismatch(c2fc_id));
+			}
+
+			match Self::owner_of_c2fc(c2fc_id) {
+				Some(owner) => {
+					if !Self::is_indexed_by::<OwnedBucketsArray<T>, _>(&owner, &c2fc_id, <OwnedBuckets<T>>::index_of(&c2fc_id)) {
+						violations.push(Violation::NotIndexed(Collection::OwnedBuckets, c2fc_id));
+					}
+					owners.insert(owner);
+				},
+				None => violations.push(Violation::MissingOwner(c2fc_id)),
+			}
+
+			match bucket.promise {
+				Some(promise) => {
+					if !<AcceptedPromiseBucket<T>>::exists(promise.id) || Self::c2fc_by_promise(promise.id) != c2fc_id {
+						violations.push(Violation::AcceptedPromiseMismatch(promise.id, c2fc_id));
+					}
+					if Self::contributor_of_c2fc(c2fc_id).as_ref() != Some(&promise.owner) {
+						violations.push(Violation::ContributorMismatch(c2fc_id));
+					}
+					if !<AcceptedPromises<T>>::contains(&promise.id) {
+						violations.push(Violation::NotIndexed(Collection::AcceptedPromises, promise.id));
+					}
+					if !Self::is_indexed_by::<IssuerPromisesArray<T>, _>(&promise.owner, &promise.id, <IssuerPromises<T>>::index_of(&promise.id)) {
+						violations.push(Violation::NotIndexed(Collection::IssuerPromises, promise.id));
+					}
+					issuers.insert(promise.owner);
+				},
+				None => if <BucketContributor<T>>::exists(c2fc_id) {
+					violations.push(Violation::ContributorMismatch(c2fc_id));
+				},
+			}
+		}
+
+		// free promises:
+		for position in 0..<FreePromises<T>>::count() {
+			let promise_id = match <FreePromises<T>>::at(position) {
+				Some(promise_id) => promise_id,
+				None => continue,
+			};
+			if <FreePromises<T>>::index_of(&promise_id) != Some(position) {
+				violations.push(Violation::IndexCorrupted(Collection::FreePromises, position, promise_id));
+			}
+			if <AcceptedPromiseBucket<T>>::exists(promise_id) {
+				violations.push(Violation::AcceptedPromiseIsFree(promise_id));
+			}
+			Self::check_promise(promise_id, Collection::FreePromises, &mut promise_owners, &mut violations);
+		}
+
+		// accepted promises:
+		for position in 0..<AcceptedPromises<T>>::count() {
+			let promise_id = match <AcceptedPromises<T>>::at(position) {
+				Some(promise_id) => promise_id,
+				None => continue,
+			};
+			if <AcceptedPromises<T>>::index_of(&promise_id) != Some(position) {
+				violations.push(Violation::IndexCorrupted(Collection::AcceptedPromises, position, promise_id));
+			}
+			let c2fc_id = Self::c2fc_by_promise(promise_id);
+			let in_bucket = <AcceptedPromiseBucket<T>>::exists(promise_id) &&
+				Self::c2fc(c2fc_id).promise.map_or(false, |promise| promise.id == promise_id);
+			if !in_bucket {
+				violations.push(Violation::AcceptedPromiseMismatch(promise_id, c2fc_id));
+			}
+			Self::check_promise(promise_id, Collection::AcceptedPromises, &mut promise_owners, &mut violations);
+		}
+
+		// per-account indexes of the found accounts:
+		for owner in owners {
+			for c2fc_id in <OwnedBuckets<T>>::items(&owner) {
+				if Self::owner_of_c2fc(c2fc_id).as_ref() != Some(&owner) {
+					violations.push(Violation::WrongIndexOwner(Collection::OwnedBuckets, owner.clone(), c2fc_id));
+				}
+			}
+		}
+		for owner in promise_owners {
+			for promise_id in <OwnedPromises<T>>::items(&owner) {
+				if !<Promises<T>>::exists(promise_id) {
+					violations.push(Violation::DanglingIndexItem(Collection::OwnedPromises, promise_id));
+				} else if Self::owner_of_promise(promise_id).as_ref() != Some(&owner) {
+					violations.push(Violation::WrongIndexOwner(Collection::OwnedPromises, owner.clone(), promise_id));
+				}
+			}
+		}
+		for issuer in issuers {
+			for promise_id in <IssuerPromises<T>>::items(&issuer) {
+				if !<AcceptedPromises<T>>::contains(&promise_id) {
+					violations.push(Violation::DanglingIndexItem(Collection::IssuerPromises, promise_id));
+				} else if Self::owner_of_promise(promise_id).as_ref() != Some(&issuer) {
+					violations.push(Violation::WrongIndexOwner(Collection::IssuerPromises, issuer.clone(), promise_id));
+				}
+			}
+		}
+
+		violations
+	}
+
+	/// Checks of free or accepted promise.
+	fn check_promise(
+		promise_id: T::Hash,
+		collection: Collection,
+		promise_owners: &mut BTreeSet<T::AccountId>,
+		violations: &mut Vec<ViolationOf<T>>,
+	) {
+		if !<Promises<T>>::exists(promise_id) {
+			violations.push(Violation::DanglingIndexItem(collection, promise_id));
+			return;
+		}
+
+		let issuer = match Self::owner_of_promise(promise_id) {
+			Some(issuer) => issuer,
+			None => {
+				violations.push(Violation::MissingOwner(promise_id));
+				return;
+			},
+		};
+		if !Self::is_indexed_by::<OwnedPromisesArray<T>, _>(&issuer, &promise_id, <OwnedPromises<T>>::index_of(&promise_id)) {
+			violations.push(Violation::NotIndexed(Collection::OwnedPromises, promise_id));
+		}
+
+		if <LockForPromise<T>>::exists(promise_id) && Self::stake_of_promise(&issuer, promise_id).is_none() {
+			violations.push(Violation::MissingLock(promise_id, issuer.clone()));
+		}
+
+		promise_owners.insert(issuer);
+	}
+
+	/// Whether the item is at the `position` of the per-account index of `key`.
+	fn is_indexed_by<Array, Key>(key: &Key, item: &T::Hash, position: Option<u64>) -> bool
+		where Key: Codec + Clone,
+		      Array: StorageMap<(Key, u64), T::Hash, Query = T::Hash>
+	{
+		position.map_or(false, |position| Array::exists((key.clone(), position)) && &Array::get((key.clone(), position)) == item)
+	}
+}
@@ -0,0 +1,188 @@
+//! Migration of the module storage to the current layout.
+//!
+//! The migration runs in `on_initialize` of the first block after the runtime upgrade
+//! and continues in the next blocks if there are more than `MIGRATION_BATCH_SIZE` entries.
+//! All calls are rejected until the migration is completed.
+
+use super::*;
+
+/// Version of the current storage layout.
+pub const STORAGE_VERSION: u32 = 1;
+
+/// Max number of entries migrated in one block.
+pub const MIGRATION_BATCH_SIZE: u64 = 64;
+
+/// Phases of the migration, the first element of `MigrationCursor`.
+pub const PHASE_BUCKETS: u32 = 0;
+pub const PHASE_PROMISES: u32 = 1;
+
+/// Layout of the version 0: promises without the revenue share.
+pub mod v0 {
+	use parity_codec::{Encode, Decode};
+
+	#[derive(Encode, Decode, Default, Clone, PartialEq)]
+	#[cfg_attr(feature = "std", derive(Debug))]
+	pub struct Bucket<Hash, Balance, AccountId, BlockNumber> {
+		pub id: Hash,
+		pub promise: Option<Promise<Hash, Balance, AccountId, BlockNumber>>,
+		pub price: Balance,
+	}
+
+	#[derive(Encode, Decode, Default, Clone, PartialEq)]
+	#[cfg_attr(feature = "std", derive(Debug))]
+	pub struct Promise<Hash, Balance, AccountId, BlockNumber> {
+		pub id: Hash,
+		pub owner: AccountId,
+		pub value: Balance,
+		pub period: BlockNumber,
+		pub until: Option<BlockNumber>,
+		pub filled: Balance,
+		pub acception_dt: BlockNumber,
+	}
+
+	#[derive(Encode, Decode, Default, Clone, PartialEq)]
+	#[cfg_attr(feature = "std", derive(Debug))]
+	pub struct FreePromise<Hash, Balance, BlockNumber> {
+		pub id: Hash,
+		pub value: Balance,
+		pub period: BlockNumber,
+		pub until: Option<BlockNumber>,
+	}
+}
+
+impl<Hash, Balance, AccountId, BlockNumber> From<v0::Promise<Hash, Balance, AccountId, BlockNumber>>
+	for Promise<Hash, Balance, AccountId, BlockNumber>
+{
+	fn from(old: v0::Promise<Hash, Balance, AccountId, BlockNumber>) -> Self {
+		Promise {
+			id: old.id,
+			owner: old.owner,
+			value: old.value,
+			period: old.period,
+			until: old.until,
+			filled: old.filled,
+			acception_dt: old.acception_dt,
+			revenue_share: None,
+		}
+	}
+}
+
+impl<Hash, Balance, AccountId, BlockNumber> From<v0::Bucket<Hash, Balance, AccountId, BlockNumber>>
+	for Bucket<Hash, Balance, AccountId, BlockNumber>
+{
+	fn from(old: v0::Bucket<Hash, Balance, AccountId, BlockNumber>) -> Self {
+		Bucket {
+			id: old.id,
+			promise: old.promise.map(Into::into),
+			price: old.price,
+		}
+	}
+}
+
+impl<Hash, Balance, BlockNumber> From<v0::FreePromise<Hash, Balance, BlockNumber>>
+	for FreePromise<Hash, Balance, BlockNumber>
+{
+	fn from(old: v0::FreePromise<Hash, Balance, BlockNumber>) -> Self {
+		FreePromise {
+			id: old.id,
+			value: old.value,
+			period: old.period,
+			until: old.until,
+			revenue_share: None,
+			verified_only: false,
+		}
+	}
+}
+
+
+impl<T: Trait> Module<T> {
+	#[inline]
+	pub fn is_storage_migrated() -> bool {
+		Self::storage_version() >= STORAGE_VERSION
+	}
+
+	pub(super) fn ensure_storage_migrated() -> Result {
+		ensure!(Self::is_storage_migrated(), "Storage migration is in progress");
+		Ok(())
+	}
+
+	/// Migrate the next batch of entries, called at the start of each block.
+	pub(super) fn migrate_storage(now: T::BlockNumber) {
+		if Self::is_storage_migrated() {
+			return;
+		}
+
+		let (mut phase, mut position) = Self::migration_cursor();
+
+		for _ in 0..MIGRATION_BATCH_SIZE {
+			match phase {
+				PHASE_BUCKETS => match <AllBuckets<T>>::at(position) {
+					Some(c2fc_id) => {
+						Self::migrate_bucket(c2fc_id, now);
+						position += 1;
+					},
+					None => {
+						phase = PHASE_PROMISES;
+						position = 0;
+					},
+				},
+				_ => match <FreePromises<T>>::at(position) {
+					// accepted promise is removed from the free promises,
+					// the last one takes its position:
+					Some(promise_id) => if Self::migrate_promise(promise_id) {
+						position += 1;
+					},
+					None => {
+						<MigrationCursor<T>>::kill();
+						<StorageVersion<T>>::put(STORAGE_VERSION);
+						Self::deposit_event(RawEvent::StorageMigrated(STORAGE_VERSION));
+						return;
+					},
+				},
+			}
+		}
+
+		<MigrationCursor<T>>::put((phase, position));
+		Self::deposit_event(RawEvent::StorageMigrationProgress(STORAGE_VERSION, phase, position));
+	}
+
+	/// Re-encode the bucket and index its accepted promise by issuer.
+	fn migrate_bucket(c2fc_id: T::Hash, now: T::BlockNumber) {
+		let old: v0::Bucket<T::Hash, T::Balance, T::AccountId, T::BlockNumber> =
+			match support::storage::get(&<Buckets<T>>::key_for(c2fc_id)) {
+				Some(old) => old,
+				None => return,
+			};
+		let bucket: Bucket<_, _, _, _> = old.into();
+
+		if let Some(ref promise) = bucket.promise {
+			if !<IssuerPromises<T>>::contains(&promise.id) {
+				let _ = <IssuerPromises<T>>::insert(&promise.owner, &promise.id);
+			}
+			// periods ended before the upgrade were already checked by the old runtime:
+			if !<ClosedPeriods<T>>::exists(promise.id) {
+				if let Some(ended) = Self::ended_periods(promise, now) {
+					<ClosedPeriods<T>>::insert(promise.id, ended);
+				}
+			}
+		}
+
+		<Buckets<T>>::insert(c2fc_id, bucket);
+	}
+
+	/// Re-encode the promise and remove it from the free promises if it is accepted.
+	/// Returns `false` if the promise is removed.
+	fn migrate_promise(promise_id: T::Hash) -> bool {
+		let old: Option<v0::FreePromise<T::Hash, T::Balance, T::BlockNumber>> =
+			support::storage::get(&<Promises<T>>::key_for(promise_id));
+		if let Some(old) = old {
+			<Promises<T>>::insert(promise_id, FreePromise::from(old));
+		}
+
+		if <AcceptedPromiseBucket<T>>::exists(promise_id) {
+			<FreePromises<T>>::remove(&promise_id).is_err()
+		} else {
+			true
+		}
+	}
+}
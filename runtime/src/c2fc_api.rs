@@ -0,0 +1,34 @@
+//! Runtime API for structured queries of the C2FC state.
+
+use rstd::prelude::*;
+use client::decl_runtime_apis;
+
+use crate::{AccountId, Balance, BlockNumber, Hash};
+use crate::c2fc;
+
+pub type BucketInfo = c2fc::BucketInfo<Hash, Balance, AccountId, BlockNumber>;
+pub type PromiseInfo = c2fc::PromiseInfo<Hash, Balance, AccountId, BlockNumber>;
+pub type PromiseFilter = c2fc::PromiseFilter<AccountId, Balance, BlockNumber>;
+pub type PromiseSchedule = c2fc::PromiseSchedule<Hash, Balance, BlockNumber>;
+
+decl_runtime_apis! {
+	/// Queries of buckets and promises.
+	/// `offset` and `limit` paginate the lists.
+	pub trait C2fcApi {
+		/// Bucket with the embedded promise.
+		fn bucket(c2fc_id: Hash) -> Option<BucketInfo>;
+		fn buckets_of_owner(owner: AccountId, offset: u64, limit: u64) -> Vec<BucketInfo>;
+		/// Buckets listed for sale.
+		fn buckets_for_sale(offset: u64, limit: u64) -> Vec<BucketInfo>;
+
+		fn promise(promise_id: Hash) -> Option<PromiseInfo>;
+		fn promises_of_owner(owner: AccountId, offset: u64, limit: u64) -> Vec<PromiseInfo>;
+		/// Accepted promises the issuer owes payments on, as `(promise_id, c2fc_id)`.
+		fn promises_owed_by(issuer: AccountId, offset: u64, limit: u64) -> Vec<(Hash, Hash)>;
+		/// Not accepted promises matching the filter.
+		fn free_promises(filter: PromiseFilter, offset: u64, limit: u64) -> Vec<PromiseInfo>;
+
+		/// Outstanding amount and the next deadline of accepted promise.
+		fn promise_schedule(promise_id: Hash) -> Option<PromiseSchedule>;
+	}
+}
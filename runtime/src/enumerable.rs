@@ -0,0 +1,380 @@
+//! Enumerable collections over `decl_storage` items.
+//!
+//! Each collection is kept in three storage items: an array of items by position,
+//! the number of items and the position of each item. Removal is done by "swap and pop",
+//! so positions of the items are not stable.
+
+use rstd::prelude::*;
+use rstd::marker::PhantomData;
+use parity_codec::Codec;
+
+use support::{StorageMap, StorageValue};
+use support::dispatch::Result;
+use support::ensure;
+
+/// Enumerable set of items:
+/// `Array: map u64 => Item`, `Count: u64`, `Index: map Item => u64`.
+pub struct EnumerableSet<Item, Array, Count, Index>(PhantomData<(Item, Array, Count, Index)>);
+
+impl<Item, Array, Count, Index> EnumerableSet<Item, Array, Count, Index>
+	where Item: Codec + Clone,
+	      Array: StorageMap<u64, Item, Query = Item>,
+	      Count: StorageValue<u64, Query = u64>,
+	      Index: StorageMap<Item, u64, Query = u64>
+{
+	pub fn count() -> u64 {
+		Count::get()
+	}
+
+	pub fn contains(item: &Item) -> bool {
+		Index::exists(item)
+	}
+
+	/// Position of the item.
+	pub fn index_of(item: &Item) -> Option<u64> {
+		if Self::contains(item) { Some(Index::get(item)) } else { None }
+	}
+
+	pub fn at(index: u64) -> Option<Item> {
+		if index < Self::count() {
+			Some(Array::get(index))
+		} else {
+			None
+		}
+	}
+
+	pub fn insert(item: &Item) -> Result {
+		ensure!(!Self::contains(item), "Item is already in the set");
+
+		let count = Self::count();
+		let new_count = count.checked_add(1)
+		                     .ok_or("Overflow adding a new item to the set")?;
+
+		Array::insert(count, item);
+		Count::put(new_count);
+		Index::insert(item, count);
+
+		Ok(())
+	}
+
+	pub fn remove(item: &Item) -> Result {
+		ensure!(Self::contains(item), "Item is not in the set");
+
+		let new_count = Self::count().checked_sub(1)
+		                             .ok_or("Underflow removing an item from the set")?;
+
+		// "Swap and pop"
+		let index = Index::get(item);
+		if index != new_count {
+			let last = Array::get(new_count);
+			Array::insert(index, &last);
+			Index::insert(&last, index);
+		}
+
+		Array::remove(new_count);
+		Count::put(new_count);
+		Index::remove(item);
+
+		Ok(())
+	}
+
+	/// Items at positions `offset .. offset + limit`.
+	pub fn page(offset: u64, limit: u64) -> Vec<Item> {
+		(offset..Self::count()).take(limit as usize)
+		                       .map(|i| Array::get(i))
+		                       .collect()
+	}
+
+	pub fn items() -> Vec<Item> {
+		Self::page(0, Self::count())
+	}
+}
+
+/// Enumerable sets of items grouped by key (e.g. by owner), each item belongs to a single key:
+/// `Array: map (Key, u64) => Item`, `Count: map Key => u64`, `Index: map Item => u64`.
+pub struct EnumerableMap<Key, Item, Array, Count, Index>(PhantomData<(Key, Item, Array, Count, Index)>);
+
+impl<Key, Item, Array, Count, Index> EnumerableMap<Key, Item, Array, Count, Index>
+	where Key: Codec + Clone,
+	      Item: Codec + Clone + PartialEq,
+	      Array: StorageMap<(Key, u64), Item, Query = Item>,
+	      Count: StorageMap<Key, u64, Query = u64>,
+	      Index: StorageMap<Item, u64, Query = u64>
+{
+	pub fn count(key: &Key) -> u64 {
+		Count::get(key)
+	}
+
+	pub fn contains(item: &Item) -> bool {
+		Index::exists(item)
+	}
+
+	/// Position of the item in the set of its key.
+	pub fn index_of(item: &Item) -> Option<u64> {
+		if Self::contains(item) { Some(Index::get(item)) } else { None }
+	}
+
+	/// Whether the item is in the set of `key`, the index alone doesn't tell the key.
+	pub fn contains_in(key: &Key, item: &Item) -> bool {
+		match Self::index_of(item) {
+			Some(index) => index < Self::count(key) && Array::get((key.clone(), index)) == *item,
+			None => false,
+		}
+	}
+
+	pub fn at(key: &Key, index: u64) -> Option<Item> {
+		if index < Self::count(key) {
+			Some(Array::get((key.clone(), index)))
+		} else {
+			None
+		}
+	}
+
+	pub fn insert(key: &Key, item: &Item) -> Result {
+		ensure!(!Self::contains(item), "Item is already in the set");
+
+		let count = Self::count(key);
+		let new_count = count.checked_add(1)
+		                     .ok_or("Overflow adding a new item to the set")?;
+
+		Array::insert((key.clone(), count), item);
+		Count::insert(key, new_count);
+		Index::insert(item, count);
+
+		Ok(())
+	}
+
+	/// Remove the item from the set of `key`.
+	pub fn remove(key: &Key, item: &Item) -> Result {
+		// with another key the swap and pop would corrupt the set of that key:
+		ensure!(Self::contains_in(key, item), "Item is not in the set of the key");
+
+		let new_count = Self::count(key).checked_sub(1)
+		                                .ok_or("Underflow removing an item from the set")?;
+
+		// "Swap and pop"
+		let index = Index::get(item);
+		if index != new_count {
+			let last = Array::get((key.clone(), new_count));
+			Array::insert((key.clone(), index), &last);
+			Index::insert(&last, index);
+		}
+
+		Array::remove((key.clone(), new_count));
+		Count::insert(key, new_count);
+		Index::remove(item);
+
+		Ok(())
+	}
+
+	/// Move the item from the set of `from` to the set of `to`.
+	pub fn transfer(from: &Key, to: &Key, item: &Item) -> Result {
+		Self::remove(from, item)?;
+		Self::insert(to, item)
+	}
+
+	/// Items of `key` at positions `offset .. offset + limit`.
+	pub fn page(key: &Key, offset: u64, limit: u64) -> Vec<Item> {
+		(offset..Self::count(key)).take(limit as usize)
+		                          .map(|i| Array::get((key.clone(), i)))
+		                          .collect()
+	}
+
+	pub fn items(key: &Key) -> Vec<Item> {
+		Self::page(key, 0, Self::count(key))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{decl_module, decl_storage, impl_outer_origin, assert_ok, assert_noop};
+	use runtime_primitives::{
+		BuildStorage,
+		traits::{BlakeTwo256, IdentityLookup},
+		testing::{Digest, DigestItem, Header}
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+
+	pub trait Trait: system::Trait {}
+	impl Trait for Test {}
+
+	decl_storage! {
+		trait Store for Module<T: Trait> as EnumerableTest {
+			SetArray: map u64 => u64;
+			SetCount: u64;
+			SetIndex: map u64 => u64;
+
+			MapArray: map (u64, u64) => u64;
+			MapCount: map u64 => u64;
+			MapIndex: map u64 => u64;
+		}
+	}
+
+	decl_module! {
+		pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
+	}
+
+	type Set = EnumerableSet<u64, SetArray<Test>, SetCount<Test>, SetIndex<Test>>;
+	type Map = EnumerableMap<u64, u64, MapArray<Test>, MapCount<Test>, MapIndex<Test>>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		system::GenesisConfig::<Test>::default().build_storage().unwrap().0.into()
+	}
+
+	#[test]
+	fn set_removes_the_last_item() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Set::insert(&10));
+			assert_ok!(Set::insert(&20));
+			assert_noop!(Set::insert(&20), "Item is already in the set");
+
+			assert_ok!(Set::remove(&20));
+			assert_eq!(Set::items(), vec![10]);
+			assert_eq!(Set::index_of(&10), Some(0));
+			assert_eq!(Set::index_of(&20), None);
+			assert!(!<SetArray<Test>>::exists(1));
+			assert_noop!(Set::remove(&20), "Item is not in the set");
+		});
+	}
+
+	#[test]
+	fn set_moves_the_last_item_into_the_removed_one() {
+		with_externalities(&mut new_test_ext(), || {
+			for item in &[10, 20, 30, 40] {
+				assert_ok!(Set::insert(item));
+			}
+
+			assert_ok!(Set::remove(&20));
+			assert_eq!(Set::items(), vec![10, 40, 30]);
+			assert_eq!(Set::index_of(&40), Some(1));
+			assert_eq!(Set::at(1), Some(40));
+			assert_eq!(Set::at(3), None);
+			assert!(!<SetArray<Test>>::exists(3));
+
+			// removal of the first and the only item:
+			assert_ok!(Set::remove(&10));
+			assert_ok!(Set::remove(&30));
+			assert_ok!(Set::remove(&40));
+			assert_eq!(Set::count(), 0);
+			assert_eq!(Set::items(), Vec::<u64>::new());
+		});
+	}
+
+	#[test]
+	fn set_page_is_bounded_by_the_count() {
+		with_externalities(&mut new_test_ext(), || {
+			for item in 0..5 {
+				assert_ok!(Set::insert(&(item * 10)));
+			}
+
+			assert_eq!(Set::page(0, 2), vec![0, 10]);
+			assert_eq!(Set::page(3, 10), vec![30, 40]);
+			assert_eq!(Set::page(5, 1), Vec::<u64>::new());
+			assert_eq!(Set::page(10, 1), Vec::<u64>::new());
+			assert_eq!(Set::page(1, 0), Vec::<u64>::new());
+			assert_eq!(Set::page(1, u64::max_value()), vec![10, 20, 30, 40]);
+		});
+	}
+
+	#[test]
+	fn map_removes_the_last_and_a_middle_item() {
+		with_externalities(&mut new_test_ext(), || {
+			for item in &[10, 20, 30] {
+				assert_ok!(Map::insert(&1, item));
+			}
+			assert_ok!(Map::insert(&2, &40));
+			assert_noop!(Map::insert(&2, &10), "Item is already in the set");
+
+			assert_ok!(Map::remove(&1, &30));
+			assert_eq!(Map::items(&1), vec![10, 20]);
+
+			assert_ok!(Map::remove(&1, &10));
+			assert_eq!(Map::items(&1), vec![20]);
+			assert_eq!(Map::index_of(&20), Some(0));
+			assert!(!<MapArray<Test>>::exists((1, 1)));
+
+			// the set of another key isn't touched:
+			assert_eq!(Map::items(&2), vec![40]);
+			assert_eq!(Map::index_of(&40), Some(0));
+		});
+	}
+
+	#[test]
+	fn map_checks_the_key_of_the_item() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Map::insert(&1, &10));
+			assert_ok!(Map::insert(&1, &20));
+			assert_ok!(Map::insert(&2, &30));
+
+			// the index of 10 (0) is valid in the set of the key 2 too:
+			assert!(Map::contains(&10));
+			assert!(Map::contains_in(&1, &10));
+			assert!(!Map::contains_in(&2, &10));
+			// the index of 20 (1) is out of the set of the key 2:
+			assert!(!Map::contains_in(&2, &20));
+			assert!(!Map::contains_in(&1, &40));
+
+			assert_noop!(Map::remove(&2, &10), "Item is not in the set of the key");
+			assert_eq!(Map::items(&2), vec![30]);
+		});
+	}
+
+	#[test]
+	fn map_transfers_the_item_between_keys() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Map::insert(&1, &10));
+			assert_ok!(Map::insert(&1, &20));
+			assert_ok!(Map::insert(&2, &30));
+
+			assert_ok!(Map::transfer(&1, &2, &10));
+			assert_eq!(Map::items(&1), vec![20]);
+			assert_eq!(Map::items(&2), vec![30, 10]);
+			assert!(Map::contains_in(&2, &10));
+			assert_eq!(Map::index_of(&10), Some(1));
+			assert_eq!(Map::index_of(&20), Some(0));
+
+			assert_noop!(Map::transfer(&1, &3, &10), "Item is not in the set of the key");
+			assert_eq!(Map::count(&3), 0);
+		});
+	}
+
+	#[test]
+	fn map_page_is_bounded_by_the_count_of_the_key() {
+		with_externalities(&mut new_test_ext(), || {
+			for item in 0..3 {
+				assert_ok!(Map::insert(&1, &item));
+			}
+			assert_ok!(Map::insert(&2, &10));
+
+			assert_eq!(Map::page(&1, 1, 5), vec![1, 2]);
+			assert_eq!(Map::page(&1, 3, 5), Vec::<u64>::new());
+			assert_eq!(Map::page(&2, 0, 5), vec![10]);
+			assert_eq!(Map::page(&3, 0, 5), Vec::<u64>::new());
+			assert_eq!(Map::at(&1, 3), None);
+		});
+	}
+}
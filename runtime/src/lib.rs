@@ -15,6 +15,9 @@ use primitives::{ed25519, sr25519, OpaqueMetadata};
 use runtime_primitives::{ApplyResult, transaction_validity::TransactionValidity, generic, create_runtime_str};
 use runtime_primitives::traits::{self, NumberFor, BlakeTwo256, Block as BlockT, DigestFor, StaticLookup, Verify};
 use grandpa::fg_primitives::{self, ScheduledChange};
+use council::{motions as council_motions, voting as council_voting, seats as council_seats};
+use council_motions::EnsureMembers;
+use primitives::u32_trait::{_2, _3};
 
 use client::{block_builder::api::{CheckInherentsResult, InherentData, self as block_builder_api},
          runtime_api, impl_runtime_apis};
@@ -71,8 +74,14 @@ pub type Nonce = u64;
 pub type Balance = u128;
 
 
+mod enumerable;
 mod c2fc;
 mod stake;
+mod multisig;
+pub mod c2fc_api;
+
+/// Limits of the C2FC market, see `set_parameters` of the `Cashflow` module.
+pub type C2fcParameters = c2fc::C2fcParameters<Balance, BlockNumber>;
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
@@ -236,6 +245,46 @@ impl finality_tracker::Trait for Runtime {
 }
 
 
+/// Referenda dispatch the proposals (e.g. C2FC parameters, pause switches
+/// and runtime upgrades) with the root origin.
+impl democracy::Trait for Runtime {
+	type Currency = Balances;
+	type Proposal = Call;
+	/// The ubiquitous event type.
+	type Event = Event;
+}
+
+impl council::Trait for Runtime {
+	/// The ubiquitous event type.
+	type Event = Event;
+	type BadPresentation = ();
+	type BadReaper = ();
+}
+
+impl council::voting::Trait for Runtime {
+	/// The ubiquitous event type.
+	type Event = Event;
+}
+
+impl council::motions::Trait for Runtime {
+	type Origin = Origin;
+	type Proposal = Call;
+	/// The ubiquitous event type.
+	type Event = Event;
+}
+
+
+impl treasury::Trait for Runtime {
+	type Currency = Balances;
+	type ApproveOrigin = EnsureMembers<_3>;
+	type RejectOrigin = EnsureMembers<_2>;
+	/// The ubiquitous event type.
+	type Event = Event;
+	type MintedForSpending = ();
+	type ProposalRejection = ();
+}
+
+
 impl sudo::Trait for Runtime {
 	/// The ubiquitous event type.
 	type Event = Event;
@@ -243,16 +292,38 @@ impl sudo::Trait for Runtime {
 }
 
 
+impl c2fc::SubmitUnsigned<c2fc::Call<Runtime>> for Runtime {
+	fn submit_unsigned(call: c2fc::Call<Runtime>) -> Result<(), ()> {
+		let extrinsic = UncheckedExtrinsic::new_unsigned(Call::Cashflow(call));
+		runtime_io::submit_extrinsic(&extrinsic);
+		Ok(())
+	}
+}
+
 impl c2fc::Trait for Runtime {
+	/// Offchain worker submits unsigned extrinsics to the local pool.
+	type SubmitTransaction = Runtime;
 	/// Currency for staking
 	// TODO: change to Currency in stake.rs mod.
 	// type Stake = Stake;
 	type Stake = Balances;
+	/// Protocol fees go to the stake module: `stake::FeeShare` of them funds the rewards,
+	/// only the rest reaches the treasury (see `stake::Trait::OnFeeRemainder`).
+	type OnFee = Stake;
 	/// The ubiquitous event type.
 	type Event = Event;
 }
 
 impl stake::Trait for Runtime {
+	/// Protocol fees above `FeeShare`, not kept for the rewards.
+	type OnFeeRemainder = Treasury;
+	/// The ubiquitous event type.
+	type Event = Event;
+}
+
+impl multisig::Trait for Runtime {
+	/// Multisig accounts can dispatch any call of the runtime.
+	type Proposal = Call;
 	/// The ubiquitous event type.
 	type Event = Event;
 }
@@ -280,15 +351,22 @@ construct_runtime!(
 		Balances: balances,
 		// Assets: assets::{Module, Event<T>, AssetId},
 		// Assets: assets::{Module, Call, Storage, Event<T>, AssetId},
-		// TODO: Treasury: treasury,
+		Treasury: treasury,
 		Session: session,
 		Staking: staking::{default, OfflineWorker},
 		FinalityTracker: finality_tracker::{Module, Call, Inherent},
 		Grandpa: grandpa::{Module, Call, Storage, Config<T>, Log(), Event<T>},
+		Democracy: democracy,
+		Council: council::{Module, Call, Storage, Event<T>},
+		CouncilVoting: council_voting,
+		CouncilMotions: council_motions::{Module, Call, Storage, Event<T>, Origin},
+		CouncilSeats: council_seats::{Config<T>},
+		// optional, the chain can start without the root key:
 		Sudo: sudo,
 		// C2FC:
-		Cashflow: c2fc::{Module, Call, Storage, Event<T>, Bucket},
-		Stake: stake::{Module, Call, Storage, Event<T>},
+		Cashflow: c2fc::{Module, Call, Storage, Config<T>, Event<T>, Bucket},
+		Stake: stake::{Module, Call, Storage, Config<T>, Event<T>},
+		Multisig: multisig::{Module, Call, Storage, Event<T>},
 		// Token: token::{Module, Call, Storage, Event<T>},
 	}
 );
@@ -362,6 +440,24 @@ impl_runtime_apis! {
 
 	impl runtime_api::TaggedTransactionQueue<Block> for Runtime {
 		fn validate_transaction(tx: <Block as BlockT>::Extrinsic) -> TransactionValidity {
+			if let Call::Cashflow(ref call) = tx.function {
+				// unsigned calls of the offchain worker have their own validation:
+				if tx.signature.is_none() {
+					return Cashflow::validate_unsigned(call);
+				}
+				// paused calls are rejected before the inclusion:
+				if Cashflow::is_call_paused(call) {
+					return TransactionValidity::Invalid(c2fc::PAUSED_CALL);
+				}
+				// as well as the calls rejected by the dispatch of the module:
+				if let Some((ref address, _, _, _)) = tx.signature {
+					let allowed = Indices::lookup(address.clone())
+						.and_then(|sender| Cashflow::ensure_call_allowed(&sender, call));
+					if allowed.is_err() {
+						return TransactionValidity::Invalid(c2fc::REJECTED_CALL);
+					}
+				}
+			}
 			Executive::validate_transaction(tx)
 		}
 	}
@@ -415,4 +511,38 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl c2fc_api::C2fcApi<Block> for Runtime {
+		fn bucket(c2fc_id: Hash) -> Option<c2fc_api::BucketInfo> {
+			Cashflow::bucket_info(c2fc_id)
+		}
+
+		fn buckets_of_owner(owner: AccountId, offset: u64, limit: u64) -> Vec<c2fc_api::BucketInfo> {
+			Cashflow::buckets_of_owner(owner, offset, limit)
+		}
+
+		fn buckets_for_sale(offset: u64, limit: u64) -> Vec<c2fc_api::BucketInfo> {
+			Cashflow::buckets_for_sale(offset, limit)
+		}
+
+		fn promise(promise_id: Hash) -> Option<c2fc_api::PromiseInfo> {
+			Cashflow::promise_info(promise_id)
+		}
+
+		fn promises_of_owner(owner: AccountId, offset: u64, limit: u64) -> Vec<c2fc_api::PromiseInfo> {
+			Cashflow::promises_of_owner(owner, offset, limit)
+		}
+
+		fn promises_owed_by(issuer: AccountId, offset: u64, limit: u64) -> Vec<(Hash, Hash)> {
+			Cashflow::promises_owed_by(issuer, offset, limit)
+		}
+
+		fn free_promises(filter: c2fc_api::PromiseFilter, offset: u64, limit: u64) -> Vec<c2fc_api::PromiseInfo> {
+			Cashflow::free_promises(filter, offset, limit)
+		}
+
+		fn promise_schedule(promise_id: Hash) -> Option<c2fc_api::PromiseSchedule> {
+			Cashflow::promise_schedule(promise_id)
+		}
+	}
+
 }
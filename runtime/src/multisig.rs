@@ -0,0 +1,402 @@
+//! Multisig accounts: calls are dispatched from the multisig account
+//! when enough of its signatories approve them.
+//!
+//! Multisig account is created with an endowment from the creator,
+//! so it is registered by `Indices` and gets a short address like any other account.
+
+use rstd::prelude::*;
+use runtime_primitives::traits::{Hash, Zero, StaticLookup};
+use support::StorageMap;
+use support::StorageValue;
+use support::{Parameter, Dispatchable};
+use support::dispatch::Result;
+use support::{decl_module, decl_storage, decl_event, ensure};
+use support::traits::{Currency, ReservableCurrency};
+use system::ensure_signed;
+
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use parity_codec::{Encode, Decode};
+
+
+/// Max number of signatories of a multisig account.
+const MAX_SIGNATORIES: usize = 32;
+
+/// Signatories of the multisig account.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize, Debug))]
+pub struct MultisigConfig<AccountId> {
+	/// sorted, without duplicates
+	pub signatories: Vec<AccountId>,
+	/// number of approvals to dispatch a call
+	pub threshold: u32,
+}
+
+/// Call of the multisig account waiting for approvals.
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Operation<AccountId, Balance, Call> {
+	pub call: Call,
+	/// signatory who proposed the call
+	pub proposer: AccountId,
+	pub approvals: Vec<AccountId>,
+	/// funds of the multisig account reserved for the call until it is dispatched
+	pub reserved: Balance,
+}
+
+type BalanceOf<T> = <T as balances::Trait>::Balance;
+type OperationOf<T> = Operation<<T as system::Trait>::AccountId, BalanceOf<T>, <T as Trait>::Proposal>;
+
+pub trait Trait: system::Trait + balances::Trait {
+	/// Calls dispatched from the multisig accounts.
+	type Proposal: Parameter + Dispatchable<Origin = <Self as system::Trait>::Origin>;
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+}
+
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as system::Trait>::AccountId,
+		<T as system::Trait>::Hash,
+	{
+		/// (multisig:AccountId, creator:AccountId)
+		MultisigCreated(AccountId, AccountId),
+		/// (multisig:AccountId, operation_id:Hash, proposer:AccountId)
+		OperationProposed(AccountId, Hash, AccountId),
+		/// (multisig:AccountId, operation_id:Hash, signatory:AccountId)
+		OperationApproved(AccountId, Hash, AccountId),
+		/// (multisig:AccountId, operation_id:Hash, result:bool)
+		OperationExecuted(AccountId, Hash, bool),
+		/// (multisig:AccountId, operation_id:Hash)
+		OperationCancelled(AccountId, Hash),
+	}
+);
+
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Multisig {
+		Multisigs get(multisig): map T::AccountId => Option<MultisigConfig<T::AccountId>>;
+		/// (multisig, operation_id) -> operation
+		Operations get(operation): map (T::AccountId, T::Hash) => Option<OperationOf<T>>;
+
+		Nonce: u64;
+	}
+}
+
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event<T>() = default;
+
+		/// Create the multisig account and transfer the `endowment` to it.
+		/// The endowment should be enough to create the account.
+		fn create_multisig(origin, signatories: Vec<<T::Lookup as StaticLookup>::Source>, threshold: u32, endowment: BalanceOf<T>) -> Result {
+			let sender = ensure_signed(origin)?;
+			ensure!(endowment >= <balances::Module<T> as Currency<T::AccountId>>::minimum_balance(),
+			        "Endowment is less than the existential deposit");
+
+			let mut signatories = signatories.into_iter()
+				.map(T::Lookup::lookup)
+				.collect::<rstd::result::Result<Vec<_>, _>>()?;
+			signatories.sort();
+			signatories.dedup();
+			ensure!(!signatories.is_empty(), "Multisig should have signatories");
+			ensure!(signatories.len() <= MAX_SIGNATORIES, "Too many signatories");
+			ensure!(threshold > 0 && threshold as usize <= signatories.len(),
+			        "Threshold should be between one and the number of signatories");
+
+			let nonce = <Nonce<T>>::get();
+			let multisig = Self::multisig_account_id(&signatories, threshold, nonce);
+			ensure!(!<Multisigs<T>>::exists(&multisig), "This multisig already exists");
+
+			// the new account gets its index here:
+			<balances::Module<T> as Currency<T::AccountId>>::transfer(&sender, &multisig, endowment)?;
+
+			<Multisigs<T>>::insert(&multisig, MultisigConfig { signatories, threshold });
+			<Nonce<T>>::mutate(|n| *n += 1);
+
+			Self::deposit_event(RawEvent::MultisigCreated(multisig, sender));
+
+			Ok(())
+		}
+
+		/// Propose the call of the multisig account, the proposal counts as an approval.
+		/// `reserve` of the multisig funds is reserved until the call is dispatched or cancelled.
+		fn propose(origin, multisig: <T::Lookup as StaticLookup>::Source, call: Box<T::Proposal>, reserve: BalanceOf<T>) -> Result {
+			let sender = ensure_signed(origin)?;
+			let multisig = T::Lookup::lookup(multisig)?;
+			Self::ensure_signatory(&multisig, &sender)?;
+
+			let nonce = <Nonce<T>>::get();
+			let operation_id = (&multisig, nonce, &call).using_encoded(<T as system::Trait>::Hashing::hash);
+			ensure!(!<Operations<T>>::exists((multisig.clone(), operation_id)), "This operation already exists");
+
+			if !reserve.is_zero() {
+				<balances::Module<T> as ReservableCurrency<T::AccountId>>::reserve(&multisig, reserve)?;
+			}
+
+			<Operations<T>>::insert((multisig.clone(), operation_id), Operation {
+				call: *call,
+				proposer: sender.clone(),
+				approvals: vec![sender.clone()],
+				reserved: reserve,
+			});
+			<Nonce<T>>::mutate(|n| *n += 1);
+
+			Self::deposit_event(RawEvent::OperationProposed(multisig.clone(), operation_id, sender));
+
+			Self::execute_if_approved(multisig, operation_id);
+
+			Ok(())
+		}
+
+		fn approve(origin, multisig: <T::Lookup as StaticLookup>::Source, operation_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			let multisig = T::Lookup::lookup(multisig)?;
+			Self::ensure_signatory(&multisig, &sender)?;
+
+			let key = (multisig.clone(), operation_id);
+			let mut operation = Self::operation(&key).ok_or("This operation does not exist")?;
+			ensure!(!operation.approvals.contains(&sender), "You already approved this operation");
+
+			operation.approvals.push(sender.clone());
+			<Operations<T>>::insert(&key, operation);
+
+			Self::deposit_event(RawEvent::OperationApproved(multisig.clone(), operation_id, sender));
+
+			Self::execute_if_approved(multisig, operation_id);
+
+			Ok(())
+		}
+
+		/// Cancel not yet dispatched operation, only the proposer can do it.
+		fn cancel(origin, multisig: <T::Lookup as StaticLookup>::Source, operation_id: T::Hash) -> Result {
+			let sender = ensure_signed(origin)?;
+			let multisig = T::Lookup::lookup(multisig)?;
+
+			let key = (multisig.clone(), operation_id);
+			let operation = Self::operation(&key).ok_or("This operation does not exist")?;
+			ensure!(operation.proposer == sender, "Only the proposer can cancel the operation");
+
+			<Operations<T>>::remove(&key);
+			<balances::Module<T> as ReservableCurrency<T::AccountId>>::unreserve(&multisig, operation.reserved);
+
+			Self::deposit_event(RawEvent::OperationCancelled(multisig, operation_id));
+
+			Ok(())
+		}
+	}
+}
+
+
+impl<T: Trait> Module<T> {
+	/// Account of the multisig, derived from its signatories.
+	pub fn multisig_account_id(signatories: &[T::AccountId], threshold: u32, nonce: u64) -> T::AccountId {
+		let entropy = (b"multisig", signatories, threshold, nonce).using_encoded(<T as system::Trait>::Hashing::hash);
+		T::AccountId::decode(&mut entropy.as_ref()).unwrap_or_default()
+	}
+
+	fn ensure_signatory(multisig: &T::AccountId, who: &T::AccountId) -> Result {
+		let config = Self::multisig(multisig).ok_or("This multisig does not exist")?;
+		ensure!(config.signatories.binary_search(who).is_ok(), "You are not a signatory of this multisig");
+		Ok(())
+	}
+
+	/// Dispatch the call from the multisig account if it has enough approvals.
+	fn execute_if_approved(multisig: T::AccountId, operation_id: T::Hash) {
+		let threshold = match Self::multisig(&multisig) {
+			Some(config) => config.threshold,
+			None => return,
+		};
+		let key = (multisig.clone(), operation_id);
+		let operation = match Self::operation(&key) {
+			Some(ref operation) if operation.approvals.len() >= threshold as usize => operation.clone(),
+			_ => return,
+		};
+
+		<Operations<T>>::remove(&key);
+		// the reserved funds are spent by the call:
+		<balances::Module<T> as ReservableCurrency<T::AccountId>>::unreserve(&multisig, operation.reserved);
+
+		let ok = operation.call.dispatch(system::RawOrigin::Signed(multisig.clone()).into()).is_ok();
+
+		Self::deposit_event(RawEvent::OperationExecuted(multisig, operation_id, ok));
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::c2fc;
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, impl_outer_dispatch, assert_ok, assert_noop};
+	use runtime_primitives::{
+		BuildStorage,
+		traits::{BlakeTwo256, IdentityLookup},
+		testing::{Digest, DigestItem, Header}
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	impl_outer_dispatch! {
+		pub enum TestCall for Test where origin: Origin {
+			balances::Balances,
+			c2fc::Cashflow,
+		}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type Event = ();
+		type TransactionPayment = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+	}
+	impl c2fc::SubmitUnsigned<c2fc::Call<Test>> for Test {
+		fn submit_unsigned(_call: c2fc::Call<Test>) -> rstd::result::Result<(), ()> {
+			Ok(())
+		}
+	}
+	impl c2fc::Trait for Test {
+		type SubmitTransaction = Test;
+		type Stake = balances::Module<Test>;
+		type OnFee = ();
+		type Event = ();
+	}
+	impl Trait for Test {
+		type Proposal = TestCall;
+		type Event = ();
+	}
+	type MultisigModule = Module<Test>;
+	type Balances = balances::Module<Test>;
+	type Cashflow = c2fc::Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+		t.extend(balances::GenesisConfig::<Test> {
+			balances: vec![(1, 1000), (2, 1000), (3, 1000)],
+			transaction_base_fee: 0,
+			transaction_byte_fee: 0,
+			existential_deposit: 0,
+			transfer_fee: 0,
+			creation_fee: 0,
+			vesting: vec![],
+		}.build_storage().unwrap().0);
+		t.extend(c2fc::GenesisConfig::<Test>::default().build_storage().unwrap().0);
+		t.into()
+	}
+
+	/// 2-of-3 multisig of the accounts 1, 2 and 3 with 100 of endowment.
+	fn new_multisig() -> u64 {
+		assert_ok!(MultisigModule::create_multisig(Origin::signed(1), vec![3, 2, 1], 2, 100));
+		MultisigModule::multisig_account_id(&[1, 2, 3], 2, 0)
+	}
+
+	fn transfer(to: u64, value: u64) -> Box<TestCall> {
+		Box::new(TestCall::Balances(balances::Call::transfer(to, value)))
+	}
+
+	#[test]
+	fn create_multisig_works() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(MultisigModule::create_multisig(Origin::signed(1), vec![], 1, 100),
+			             "Multisig should have signatories");
+			assert_noop!(MultisigModule::create_multisig(Origin::signed(1), vec![1, 2, 2], 3, 100),
+			             "Threshold should be between one and the number of signatories");
+
+			let multisig = new_multisig();
+			assert_eq!(MultisigModule::multisig(multisig), Some(MultisigConfig { signatories: vec![1, 2, 3], threshold: 2 }));
+			assert_eq!(Balances::free_balance(&multisig), 100);
+			assert_eq!(Balances::free_balance(&1), 900);
+		});
+	}
+
+	#[test]
+	fn call_is_dispatched_with_enough_approvals() {
+		with_externalities(&mut new_test_ext(), || {
+			let multisig = new_multisig();
+
+			assert_noop!(MultisigModule::propose(Origin::signed(4), multisig, transfer(4, 60), 60),
+			             "You are not a signatory of this multisig");
+			assert_ok!(MultisigModule::propose(Origin::signed(1), multisig, transfer(4, 60), 60));
+			assert_eq!(Balances::reserved_balance(&multisig), 60);
+			assert_eq!(Balances::free_balance(&multisig), 40);
+
+			let operation_id = (multisig, 1u64, transfer(4, 60)).using_encoded(BlakeTwo256::hash);
+			assert_eq!(MultisigModule::operation((multisig, operation_id)).unwrap().approvals, vec![1]);
+			assert_noop!(MultisigModule::approve(Origin::signed(1), multisig, operation_id),
+			             "You already approved this operation");
+
+			assert_ok!(MultisigModule::approve(Origin::signed(3), multisig, operation_id));
+			assert_eq!(MultisigModule::operation((multisig, operation_id)), None);
+			assert_eq!(Balances::reserved_balance(&multisig), 0);
+			assert_eq!(Balances::free_balance(&multisig), 40);
+			assert_eq!(Balances::free_balance(&4), 60);
+
+			assert_noop!(MultisigModule::approve(Origin::signed(2), multisig, operation_id),
+			             "This operation does not exist");
+		});
+	}
+
+	#[test]
+	fn cancel_unreserves_the_funds() {
+		with_externalities(&mut new_test_ext(), || {
+			let multisig = new_multisig();
+
+			assert_noop!(MultisigModule::propose(Origin::signed(1), multisig, transfer(4, 200), 200),
+			             "not enough free funds");
+			assert_ok!(MultisigModule::propose(Origin::signed(1), multisig, transfer(4, 60), 60));
+			let operation_id = (multisig, 1u64, transfer(4, 60)).using_encoded(BlakeTwo256::hash);
+
+			assert_noop!(MultisigModule::cancel(Origin::signed(2), multisig, operation_id),
+			             "Only the proposer can cancel the operation");
+			assert_ok!(MultisigModule::cancel(Origin::signed(1), multisig, operation_id));
+			assert_eq!(MultisigModule::operation((multisig, operation_id)), None);
+			assert_eq!(Balances::free_balance(&multisig), 100);
+			assert_eq!(Balances::free_balance(&4), 0);
+		});
+	}
+
+	#[test]
+	fn multisig_buys_the_c2fc() {
+		with_externalities(&mut new_test_ext(), || {
+			let multisig = new_multisig();
+			assert_ok!(Cashflow::create_c2fc(Origin::signed(1)));
+			let c2fc_id = Cashflow::c2fc_of_owner_by_index((1, 0));
+			assert_ok!(Cashflow::set_price(Origin::signed(1), c2fc_id, 50));
+
+			let buy = Box::new(TestCall::Cashflow(c2fc::Call::buy_c2fc(c2fc_id, 50)));
+			assert_ok!(MultisigModule::propose(Origin::signed(2), multisig, buy.clone(), 50));
+			assert_eq!(Cashflow::owner_of_c2fc(c2fc_id), Some(1));
+
+			let operation_id = (multisig, 1u64, buy).using_encoded(BlakeTwo256::hash);
+			assert_ok!(MultisigModule::approve(Origin::signed(3), multisig, operation_id));
+			assert_eq!(Cashflow::owner_of_c2fc(c2fc_id), Some(multisig));
+			assert_eq!(Balances::free_balance(&multisig), 50);
+			assert_eq!(Cashflow::proceeds_of(1), 50);
+		});
+	}
+}
@@ -1,24 +1,34 @@
 // use crate::Balances;
-// use support::StorageMap;
-// use support::StorageValue;
-// use support::dispatch::Result;
+use support::StorageMap;
+use support::StorageValue;
+use support::dispatch::Result;
 use support::{decl_module, decl_storage, decl_event};
-// use support::{ensure, fail};
-// use system::{ensure_signed, ensure_root};
-// use runtime_primitives::traits::{Hash, Zero};
+use support::ensure;
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::Permill;
+use runtime_primitives::traits::{As, Zero, CheckedMul};
 // use assets::*;
 
 // #[cfg(feature = "std")]
 // use serde::{Serialize, Deserialize};
 // use parity_codec::{Encode, Decode};
 
-// use support::traits::{Currency, ReservableCurrency, OnDilution, OnUnbalanced, Imbalance};
+use support::traits::{Currency, OnUnbalanced, Imbalance};
 // use runtime_io::print;
 
+use crate::c2fc;
+
+type NegativeImbalanceOf<T> = <balances::Module<T> as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
+
+/// Kind of `Issued` event: reward for the stake locked behind a promise.
+pub const STAKE_REWARD: u16 = 0;
+
 
 // pub trait Trait: balances::Trait where Self: Currency<Self::AccountId> {
 // pub trait Trait: balances::Trait where Self: Currency<<Self as system::Trait>::AccountId> {
-pub trait Trait: balances::Trait {
+pub trait Trait: balances::Trait + c2fc::Trait {
+	/// Receiver of the protocol fees not kept for the rewards (e.g. treasury).
+	type OnFeeRemainder: OnUnbalanced<NegativeImbalanceOf<Self>>;
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 }
 
@@ -26,6 +36,18 @@ pub trait Trait: balances::Trait {
 decl_storage! {
 	trait Store for Module<T: Trait> as Akt {
 		Stake get(value): map T::AccountId => T::Balance;
+
+		/// Number of blocks between two distributions of rewards.
+		/// Zero disables the rewards.
+		RewardPeriod get(reward_period) config(): T::BlockNumber;
+		/// Part of the collateral in good standing minted as a reward each period.
+		InflationRate get(inflation_rate) config(): Permill;
+		/// Part of the protocol fees kept for the rewards, the rest goes to `T::OnFeeRemainder`.
+		FeeShare get(fee_share) config(): Permill;
+		/// Protocol fees collected for the next distribution.
+		RewardPool get(reward_pool): T::Balance;
+		/// Issued but not yet claimed rewards.
+		UnclaimedRewards get(unclaimed_rewards): map T::AccountId => T::Balance;
 	}
 }
 
@@ -33,6 +55,52 @@ decl_module! {
 	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
 		fn deposit_event<T>() = default;
 
+		fn set_reward_period(origin, period: T::BlockNumber) -> Result {
+			ensure_root(origin)?;
+			<RewardPeriod<T>>::put(period);
+			Self::deposit_event(RawEvent::RewardPeriodSet(period));
+			Ok(())
+		}
+
+		fn set_inflation_rate(origin, rate: Permill) -> Result {
+			ensure_root(origin)?;
+			<InflationRate<T>>::put(rate);
+			Self::deposit_event(RawEvent::InflationRateSet(rate));
+			Ok(())
+		}
+
+		fn set_fee_share(origin, share: Permill) -> Result {
+			ensure_root(origin)?;
+			<FeeShare<T>>::put(share);
+			Self::deposit_event(RawEvent::FeeShareSet(share));
+			Ok(())
+		}
+
+		/// Move all issued rewards of the sender to its free balance.
+		/// Rewards below the existential deposit of a new account are kept until they grow.
+		fn claim_rewards(origin) -> Result {
+			let sender = ensure_signed(origin)?;
+
+			let reward = Self::unclaimed_rewards(&sender);
+			ensure!(!reward.is_zero(), "No rewards to claim");
+			let account_exists = !<balances::Module<T> as Currency<_>>::total_balance(&sender).is_zero();
+			ensure!(account_exists || reward >= <balances::Module<T> as Currency<_>>::minimum_balance(),
+			        "Rewards are below the existential deposit");
+
+			<UnclaimedRewards<T>>::remove(&sender);
+			let _ = <balances::Module<T> as Currency<_>>::deposit_creating(&sender, reward);
+
+			Self::deposit_event(RawEvent::RewardClaimed(sender, reward));
+
+			Ok(())
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			let period = Self::reward_period();
+			if !period.is_zero() && (n % period).is_zero() {
+				Self::distribute_rewards();
+			}
+		}
 	}
 }
 
@@ -41,9 +109,304 @@ decl_event!(
 	pub enum Event<T>
 		where Balance = <T as balances::Trait>::Balance,
 		      AccountId = <T as system::Trait>::AccountId,
+		      BlockNumber = <T as system::Trait>::BlockNumber,
 	{
+		/// (kind:u16, account:AccountId, amount:u64), see `STAKE_REWARD`
 		Issued(u16, AccountId, u64),
+		/// (issuer:AccountId, reward:Balance)
+		RewardClaimed(AccountId, Balance),
+		/// (period:BlockNumber)
+		RewardPeriodSet(BlockNumber),
+		/// (rate:Permill)
+		InflationRateSet(Permill),
+		/// (share:Permill)
+		FeeShareSet(Permill),
 		Stake(Balance, AccountId),
 		Withdraw(Balance, AccountId),
 	}
 );
+
+
+impl<T: Trait> Module<T> {
+	/// Share the pool (collected fees + inflation) pro rata
+	/// between stakes locked behind promises in good standing.
+	/// Promises with arrears get nothing.
+	fn distribute_rewards() {
+		let collateral = <c2fc::Module<T>>::collateral_in_good_standing();
+
+		let total = collateral.iter()
+		                      .fold(T::Balance::zero(), |total, (_, _, stake)| total + *stake);
+		if total.is_zero() {
+			return;
+		}
+
+		let pool = Self::reward_pool() + Self::inflation_rate() * total;
+		if pool.is_zero() {
+			return;
+		}
+
+		let mut issued = T::Balance::zero();
+		for (issuer, _, stake) in collateral {
+			let reward = pro_rata(pool, stake, total);
+			if reward.is_zero() {
+				continue;
+			}
+
+			<UnclaimedRewards<T>>::mutate(&issuer, |unclaimed| *unclaimed += reward);
+			issued += reward;
+
+			let amount = if reward > T::Balance::sa(u64::max_value()) { u64::max_value() } else { reward.as_() };
+			Self::deposit_event(RawEvent::Issued(STAKE_REWARD, issuer, amount));
+		}
+
+		// collected fees are spent first, the rest is minted on claim:
+		let fees = Self::reward_pool();
+		<RewardPool<T>>::put(if issued < fees { fees - issued } else { T::Balance::zero() });
+	}
+}
+
+/// `amount * part / total` without overflow of the intermediate product.
+fn pro_rata<B>(amount: B, part: B, total: B) -> B
+	where B: Copy + Zero + CheckedMul + rstd::ops::Div<Output = B> + rstd::ops::Mul<Output = B>
+{
+	amount.checked_mul(&part)
+	      .map(|x| x / total)
+	      .unwrap_or_else(|| amount / total * part)
+}
+
+/// Protocol fees routed to this module fund the rewards,
+/// the part above `FeeShare` is passed to `T::OnFeeRemainder`.
+impl<T: Trait> OnUnbalanced<NegativeImbalanceOf<T>> for Module<T> {
+	fn on_unbalanced(amount: NegativeImbalanceOf<T>) {
+		let kept = Self::fee_share() * amount.peek();
+		let (kept, remainder) = amount.split(kept);
+		T::OnFeeRemainder::on_unbalanced(remainder);
+
+		// the kept imbalance is dropped, so the funds leave the total issuance
+		// until they are claimed as rewards.
+		<RewardPool<T>>::mutate(|pool| *pool += kept.peek());
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, assert_ok, assert_noop};
+	use runtime_primitives::{
+		BuildStorage,
+		traits::{BlakeTwo256, IdentityLookup, OnFinalize},
+		testing::{Digest, DigestItem, Header}
+	};
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type Digest = Digest;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = ();
+		type Log = DigestItem;
+	}
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type Event = ();
+		type TransactionPayment = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+	}
+	impl c2fc::SubmitUnsigned<c2fc::Call<Test>> for Test {
+		fn submit_unsigned(_call: c2fc::Call<Test>) -> rstd::result::Result<(), ()> {
+			Ok(())
+		}
+	}
+	impl c2fc::Trait for Test {
+		type SubmitTransaction = Test;
+		type Stake = balances::Module<Test>;
+		type OnFee = StakeModule;
+		type Event = ();
+	}
+
+	/// Account receiving the fees not kept for the rewards.
+	const TREASURY: u64 = 100;
+
+	pub struct Treasury;
+	impl OnUnbalanced<NegativeImbalanceOf<Test>> for Treasury {
+		fn on_unbalanced(fee: NegativeImbalanceOf<Test>) {
+			let _ = Balances::deposit_creating(&TREASURY, fee.peek());
+		}
+	}
+
+	impl Trait for Test {
+		type OnFeeRemainder = Treasury;
+		type Event = ();
+	}
+	type StakeModule = Module<Test>;
+	type Cashflow = c2fc::Module<Test>;
+	type Balances = balances::Module<Test>;
+
+	fn new_test_ext(existential_deposit: u64, stake: GenesisConfig<Test>) -> runtime_io::TestExternalities<Blake2Hasher> {
+		let (mut t, mut children) = system::GenesisConfig::<Test>::default().build_storage().unwrap();
+		balances::GenesisConfig::<Test> {
+			balances: vec![(1, 1000), (2, 1000), (3, 1000), (4, 1000)],
+			transaction_base_fee: 0,
+			transaction_byte_fee: 0,
+			existential_deposit,
+			transfer_fee: 0,
+			creation_fee: 0,
+			vesting: vec![],
+		}.assimilate_storage(&mut t, &mut children).unwrap();
+		c2fc::GenesisConfig::<Test>::default().assimilate_storage(&mut t, &mut children).unwrap();
+		stake.assimilate_storage(&mut t, &mut children).unwrap();
+		t.into()
+	}
+
+	/// Rewards each 5 blocks, `rate` of the stake minted and `share` of the fees kept.
+	fn rewards(rate: u32, share: u32) -> GenesisConfig<Test> {
+		GenesisConfig::<Test> {
+			reward_period: 5,
+			inflation_rate: Permill::from_percent(rate),
+			fee_share: Permill::from_percent(share),
+		}
+	}
+
+	/// Promise of `issuer` with `stake` accepted by the account 1.
+	fn staked_promise(issuer: u64, stake: u64) -> (H256, H256) {
+		assert_ok!(Cashflow::create_c2fc(Origin::signed(1)));
+		let c2fc_id = Cashflow::c2fc_of_owner_by_index((1, Cashflow::owned_c2fc_count(1) - 1));
+		assert_ok!(Cashflow::create_promise_until(Origin::signed(issuer), 10, 5, 0, false));
+		let promise_id = Cashflow::promise_of_owner_by_index((issuer, Cashflow::owned_promise_count(issuer) - 1));
+		assert_ok!(Cashflow::stake_to_promise(Origin::signed(issuer), promise_id, stake));
+		assert_ok!(Cashflow::accept_promise(Origin::signed(1), promise_id, c2fc_id));
+		(c2fc_id, promise_id)
+	}
+
+	fn run_to_block(n: u64) {
+		let mut block = system::Module::<Test>::block_number();
+		while block < n {
+			block += 1;
+			system::Module::<Test>::set_block_number(block);
+			Cashflow::on_finalize(block);
+			StakeModule::on_finalize(block);
+		}
+	}
+
+	#[test]
+	fn setters_are_root_only() {
+		with_externalities(&mut new_test_ext(0, rewards(0, 0)), || {
+			assert!(StakeModule::set_reward_period(Origin::signed(1), 10).is_err());
+			assert_ok!(StakeModule::set_reward_period(Origin::ROOT, 10));
+			assert_ok!(StakeModule::set_inflation_rate(Origin::ROOT, Permill::from_percent(1)));
+			assert_ok!(StakeModule::set_fee_share(Origin::ROOT, Permill::from_percent(30)));
+
+			assert_eq!(StakeModule::reward_period(), 10);
+			assert_eq!(StakeModule::inflation_rate(), Permill::from_percent(1));
+			assert_eq!(StakeModule::fee_share(), Permill::from_percent(30));
+		});
+	}
+
+	#[test]
+	fn rewards_are_shared_pro_rata() {
+		with_externalities(&mut new_test_ext(0, rewards(10, 0)), || {
+			system::Module::<Test>::set_block_number(1);
+			staked_promise(2, 100);
+			staked_promise(3, 300);
+
+			run_to_block(4);
+			assert_eq!(StakeModule::unclaimed_rewards(2), 0);
+
+			// 10% of 400 staked:
+			run_to_block(5);
+			assert_eq!(StakeModule::unclaimed_rewards(2), 10);
+			assert_eq!(StakeModule::unclaimed_rewards(3), 30);
+			assert_eq!(StakeModule::reward_pool(), 0);
+
+			// the rewards are minted on claim:
+			let issuance = Balances::total_issuance();
+			assert_ok!(StakeModule::claim_rewards(Origin::signed(2)));
+			assert_eq!(Balances::free_balance(&2), 1010);
+			assert_eq!(Balances::total_issuance(), issuance + 10);
+			assert_eq!(StakeModule::unclaimed_rewards(2), 0);
+			assert_noop!(StakeModule::claim_rewards(Origin::signed(2)), "No rewards to claim");
+		});
+	}
+
+	#[test]
+	fn fees_fund_the_rewards_up_to_the_fee_share() {
+		with_externalities(&mut new_test_ext(0, rewards(0, 50)), || {
+			system::Module::<Test>::set_block_number(1);
+			staked_promise(2, 100);
+			let c2fc_id = Cashflow::c2fc_of_owner_by_index((1, 0));
+			assert_ok!(Cashflow::set_parameters(Origin::ROOT, c2fc::C2fcParameters {
+				sale_fee: Permill::from_percent(10),
+				..Cashflow::parameters()
+			}));
+			assert_ok!(Cashflow::set_price(Origin::signed(1), c2fc_id, 100));
+
+			// the sale fee of 10 is split between the rewards and the treasury:
+			let issuance = Balances::total_issuance();
+			assert_ok!(Cashflow::buy_c2fc(Origin::signed(4), c2fc_id, 100));
+			assert_eq!(StakeModule::reward_pool(), 5);
+			assert_eq!(Balances::free_balance(&TREASURY), 5);
+			assert_eq!(Balances::total_issuance(), issuance - 5);
+
+			// no inflation, the rewards are paid from the fees only:
+			run_to_block(5);
+			assert_eq!(StakeModule::unclaimed_rewards(2), 5);
+			assert_eq!(StakeModule::reward_pool(), 0);
+			assert_ok!(StakeModule::claim_rewards(Origin::signed(2)));
+			assert_eq!(Balances::total_issuance(), issuance);
+		});
+	}
+
+	#[test]
+	fn promise_in_arrears_gets_no_reward() {
+		with_externalities(&mut new_test_ext(0, rewards(10, 0)), || {
+			system::Module::<Test>::set_block_number(1);
+			staked_promise(2, 100);
+			let (c2fc_id, _) = staked_promise(3, 300);
+			assert_ok!(Cashflow::fill_c2fc(Origin::signed(3), c2fc_id, 10));
+			assert_ok!(StakeModule::set_reward_period(Origin::ROOT, 10));
+
+			// the breach isn't compensated while the filling is paused, the arrears are kept:
+			assert_ok!(Cashflow::pause(Origin::ROOT, c2fc::PauseCategory::Filling));
+			run_to_block(10);
+			assert_eq!(StakeModule::unclaimed_rewards(2), 0);
+			assert_eq!(StakeModule::unclaimed_rewards(3), 30);
+		});
+	}
+
+	#[test]
+	fn rewards_below_the_existential_deposit_are_kept() {
+		with_externalities(&mut new_test_ext(20, rewards(10, 0)), || {
+			// the account 5 doesn't exist:
+			<UnclaimedRewards<Test>>::insert(5, 10);
+			assert_noop!(StakeModule::claim_rewards(Origin::signed(5)), "Rewards are below the existential deposit");
+			assert_eq!(StakeModule::unclaimed_rewards(5), 10);
+
+			<UnclaimedRewards<Test>>::insert(5, 20);
+			assert_ok!(StakeModule::claim_rewards(Origin::signed(5)));
+			assert_eq!(Balances::free_balance(&5), 20);
+
+			// existing account claims any amount:
+			<UnclaimedRewards<Test>>::insert(3, 10);
+			assert_ok!(StakeModule::claim_rewards(Origin::signed(3)));
+			assert_eq!(Balances::free_balance(&3), 1010);
+		});
+	}
+}
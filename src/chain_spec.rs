@@ -1,7 +1,8 @@
-use primitives::{ed25519, sr25519, Pair};
+use primitives::{ed25519, sr25519, Pair, blake2_256};
 use primitives::crypto::UncheckedInto;
 use akropolis_runtime::{/* AuthorityId, */
                     AccountId,
+                    Hash,
                     GenesisConfig,
                     ConsensusConfig,
                     TimestampConfig,
@@ -11,9 +12,17 @@ use akropolis_runtime::{/* AuthorityId, */
                     StakingConfig,
                     StakerStatus,
                     SudoConfig,
+                    DemocracyConfig,
+                    CouncilSeatsConfig,
+                    CouncilVotingConfig,
                     IndicesConfig,
+                    StakeConfig,
+                    CashflowConfig,
+                    C2fcParameters,
+                    TreasuryConfig,
                     // AssetsConfig
-                    Perbill};
+                    Perbill,
+                    Permill};
 use substrate_service;
 use telemetry::TelemetryEndpoints;
 // use sr25519::Public as AccountId;
@@ -115,14 +124,24 @@ impl Alternative {
 							hex!["62ca01ab78f2f3e5ba59c3ddd16c1e4d07eb8021687ce22a202eba4984bc94b8"].unchecked_into(),
 						];
 
-					                        // 5EJEZLV9UNxv6HpVYxDwfQf7oDtamRU4dXG9gqx1XJ14MzWK:
-					                        let root_key = hex!["62ca01ab78f2f3e5ba59c3ddd16c1e4d07eb8021687ce22a202eba4984bc94b8"].unchecked_into();
+					                        // the operators hold the seats, as they held the `root_key` before:
+					                        let council = vec![
+							// 5EE4p6upP21hxqrKZGH1vPr4azoN63eYQT5kszmbKVvK61NL:
+							hex!["5f9c380ad795be476350d9b31f5ad771abfe728d918b7e35021259f66da17470"].unchecked_into(),
+							// 5FVEDPNip5otFuo47X4JYkfZxUPezf8QuZaYGbHWRidCmgru:
+							hex!["9768c811cf000ce59faec3de5a915193ca87e90224142bbc4117a7201e123ee6"].unchecked_into(),
+							// 5EJEZLV9UNxv6HpVYxDwfQf7oDtamRU4dXG9gqx1XJ14MzWK:
+							hex!["62ca01ab78f2f3e5ba59c3ddd16c1e4d07eb8021687ce22a202eba4984bc94b8"].unchecked_into(),
+						];
 
 					                        testnet_genesis(
 					                                        // initial_authorities.iter().map(|id| id.clone()).collect(),
 					                                        initial_authorities,
 					                                        endowed_accounts.iter().map(|id| id.clone()).collect(),
-					                                        root_key,
+					                                        // governed by the council and referenda only:
+					                                        None,
+					                                        council,
+					                                        demo_c2fc_book(),
 					)
 					                       },
 				                        vec![],
@@ -154,21 +173,63 @@ impl Alternative {
 	}
 }
 
+const MILLICENTS: u128 = 1_000_000_000;
+const CENTS: u128 = 1_000 * MILLICENTS; // assume this is worth about a cent.
+const DOLLARS: u128 = 100 * CENTS;
+
+const SECS_PER_BLOCK: u64 = 6;
+const MINUTES: u64 = 60 / SECS_PER_BLOCK;
+const HOURS: u64 = MINUTES * 60;
+const DAYS: u64 = HOURS * 24;
+
+/// Id of the bucket or promise preloaded at the genesis.
+fn genesis_id(label: &str) -> Hash {
+	blake2_256(label.as_bytes()).into()
+}
+
+/// Starting C2FC book of the testnet:
+/// Alice owns two buckets (one is for sale), Bob's promise is accepted into the first one
+/// and backed by a stake, Charlie's promise is free.
+fn demo_c2fc_book() -> CashflowConfig {
+	let alice = account_key("Alice");
+	let bob = account_key("Bob");
+	let charlie = account_key("Charlie");
+
+	let alice_bucket = genesis_id("c2fc/genesis/bucket/alice/0");
+	let alice_listing = genesis_id("c2fc/genesis/bucket/alice/1");
+	let bob_promise = genesis_id("c2fc/genesis/promise/bob/0");
+	let charlie_promise = genesis_id("c2fc/genesis/promise/charlie/0");
+
+	CashflowConfig { // local test oracle:
+	                 oracles: vec![account_key("Ferdie")],
+	                 // local test attestor:
+	                 attestors: vec![account_key("Eve")],
+	                 buckets: vec![(alice.clone(), alice_bucket, 0),
+	                               (alice, alice_listing, 500 * DOLLARS)],
+	                 promises: vec![(bob, bob_promise, 100 * DOLLARS, 7 * DAYS, 0),
+	                                (charlie, charlie_promise, 250 * DOLLARS, 30 * DAYS, 365 * DAYS)],
+	                 accepted_promises: vec![(bob_promise, alice_bucket)],
+	                 stakes: vec![(bob_promise, 1_000 * DOLLARS)],
+	                 parameters: C2fcParameters { min_period: 1 * HOURS,
+	                                              max_period: 5 * 365 * DAYS,
+	                                              max_value: 1_000_000 * DOLLARS,
+	                                              max_price: 10_000_000 * DOLLARS,
+	                                              max_promises_per_account: 100,
+	                                              report_grace: 1 * DAYS,
+	                                              sale_fee: Permill::from_percent(1),
+	                                              fill_fee: Permill::from_millionths(5_000) } }
+}
+
+/// Without the `root_key` the root calls are dispatched by referenda only.
 fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>,
-                   endowed_accounts: Vec<AccountId>, root_key: AccountId)
+                   endowed_accounts: Vec<AccountId>, root_key: Option<AccountId>,
+                   council: Vec<AccountId>,
+                   c2fc_book: CashflowConfig)
                    -> GenesisConfig
 {
-	const MILLICENTS: u128 = 1_000_000_000;
-	const CENTS: u128 = 1_000 * MILLICENTS; // assume this is worth about a cent.
-	const DOLLARS: u128 = 100 * CENTS;
-
-	const SECS_PER_BLOCK: u64 = 6;
-	const MINUTES: u64 = 60 / SECS_PER_BLOCK;
-	const HOURS: u64 = MINUTES * 60;
-	const DAYS: u64 = HOURS * 24;
-
 	const ENDOWMENT: u128 = 10_000_000 * DOLLARS;
 	const STASH: u128 = 100 * DOLLARS;
+	const COUNCIL_TERM: u64 = 28 * DAYS;
 
 	GenesisConfig { consensus: Some(ConsensusConfig { code: include_bytes!(
 		"../runtime/wasm/target/wasm32-unknown-unknown/release/akropolis_runtime_wasm.compact.wasm"
@@ -226,6 +287,38 @@ fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, AuthorityId)>
 	                                              authorities: initial_authorities.iter()
 	                                                                              .map(|x| (x.2.clone(), 1))
 	                                                                              .collect() }),
+	                treasury: Some(TreasuryConfig { proposal_bond: Permill::from_percent(5),
+	                                                proposal_bond_minimum: 1 * DOLLARS,
+	                                                spend_period: 1 * DAYS,
+	                                                burn: Permill::from_percent(0) }),
+	                stake: Some(StakeConfig { reward_period: 1 * DAYS,
+	                                          // 0.01% of the collateral in good standing per day:
+	                                          inflation_rate: Permill::from_millionths(100),
+	                                          // half of the protocol fees, the rest goes to the treasury:
+	                                          fee_share: Permill::from_percent(50) }),
+	                democracy: Some(DemocracyConfig { launch_period: 1 * DAYS,
+	                                                  voting_period: 3 * DAYS,
+	                                                  minimum_deposit: 100 * DOLLARS,
+	                                                  public_delay: 1 * DAYS,
+	                                                  max_lock_periods: 6 }),
+	                council_seats: Some(CouncilSeatsConfig { active_council: council.iter()
+	                                                                         .cloned()
+	                                                                         .map(|member| (member, COUNCIL_TERM))
+	                                                                         .collect(),
+	                                                         candidacy_bond: 10 * DOLLARS,
+	                                                         voter_bond: 1 * DOLLARS,
+	                                                         present_slash_per_voter: 1 * CENTS,
+	                                                         carry_count: 6,
+	                                                         presentation_duration: 1 * DAYS,
+	                                                         approval_voting_period: 2 * DAYS,
+	                                                         term_duration: COUNCIL_TERM,
+	                                                         desired_seats: council.len() as u32,
+	                                                         // one more vote goes by before an inactive voter can be reaped:
+	                                                         inactive_grace_period: 1 }),
+	                council_voting: Some(CouncilVotingConfig { cooloff_period: 4 * DAYS,
+	                                                           voting_period: 1 * DAYS,
+	                                                           enact_delay_period: 0 }),
+	                cashflow: Some(c2fc_book),
 	                // TODO: assets: Some(AssetsConfig{}),
-	                sudo: Some(SudoConfig { key: root_key }) }
+	                sudo: root_key.map(|key| SudoConfig { key }) }
 }
@@ -10,6 +10,7 @@ mod consts;
 // mod error;
 mod chain_spec;
 mod service;
+mod rpc;
 mod cli;
 
 pub use substrate_cli::{VersionInfo, IntoExit, error};
@@ -0,0 +1,145 @@
+//! JSON-RPC methods of the node exposing the `C2fcApi` runtime API.
+
+use std::sync::Arc;
+
+use akropolis_runtime::{AccountId, Hash, opaque::{Block, BlockId}};
+use akropolis_runtime::c2fc_api::{C2fcApi as C2fcRuntimeApi, BucketInfo, PromiseInfo, PromiseFilter, PromiseSchedule};
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use primitives::{Blake2Hasher, H256};
+use substrate_client::{self as client, Client, CallExecutor, backend::Backend, runtime_api::ProvideRuntimeApi};
+use substrate_rpc::Metadata;
+
+/// Page size used when the limit isn't specified.
+const DEFAULT_PAGE_SIZE: u64 = 100;
+
+/// C2FC RPC methods.
+#[rpc]
+pub trait C2fcApi {
+	/// Bucket with the embedded promise.
+	#[rpc(name = "c2fc_getBucket")]
+	fn bucket(&self, c2fc_id: Hash, at: Option<H256>) -> Result<Option<BucketInfo>>;
+
+	#[rpc(name = "c2fc_bucketsOfOwner")]
+	fn buckets_of_owner(&self, owner: AccountId, offset: Option<u64>, limit: Option<u64>, at: Option<H256>)
+	                    -> Result<Vec<BucketInfo>>;
+
+	#[rpc(name = "c2fc_promisesOfOwner")]
+	fn promises_of_owner(&self, owner: AccountId, offset: Option<u64>, limit: Option<u64>, at: Option<H256>)
+	                     -> Result<Vec<PromiseInfo>>;
+
+	/// Not accepted promises matching the filter.
+	#[rpc(name = "c2fc_freePromises")]
+	fn free_promises(&self, filter: Option<PromiseFilter>, offset: Option<u64>, limit: Option<u64>, at: Option<H256>)
+	                 -> Result<Vec<PromiseInfo>>;
+
+	/// Outstanding amount and the next deadline of accepted promise.
+	#[rpc(name = "c2fc_promiseSchedule")]
+	fn promise_schedule(&self, promise_id: Hash, at: Option<H256>) -> Result<Option<PromiseSchedule>>;
+
+	/// Buckets listed for sale.
+	#[rpc(name = "c2fc_marketListings")]
+	fn market_listings(&self, offset: Option<u64>, limit: Option<u64>, at: Option<H256>) -> Result<Vec<BucketInfo>>;
+}
+
+/// Implementation of the C2FC RPC methods over the client.
+pub struct C2fc<B, E, RA> {
+	client: Arc<Client<B, E, Block, RA>>,
+}
+
+impl<B, E, RA> C2fc<B, E, RA> {
+	pub fn new(client: Arc<Client<B, E, Block, RA>>) -> Self {
+		C2fc { client }
+	}
+}
+
+impl<B, E, RA> C2fc<B, E, RA>
+	where B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	      E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	      RA: Send + Sync + 'static
+{
+	/// Requested block or the best one.
+	fn block_id(&self, at: Option<H256>) -> Result<BlockId> {
+		let hash = match at {
+			Some(hash) => hash,
+			None => self.client.info().map_err(client_error)?.chain.best_hash,
+		};
+		Ok(BlockId::hash(hash))
+	}
+}
+
+fn client_error(e: client::error::Error) -> Error {
+	Error { code: ErrorCode::ServerError(1),
+	        message: "Unable to query the runtime".into(),
+	        data: Some(format!("{:?}", e).into()) }
+}
+
+impl<B, E, RA> C2fcApi for C2fc<B, E, RA>
+	where B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	      E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	      RA: Send + Sync + 'static,
+	      Client<B, E, Block, RA>: ProvideRuntimeApi,
+	      <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: C2fcRuntimeApi<Block>
+{
+	fn bucket(&self, c2fc_id: Hash, at: Option<H256>) -> Result<Option<BucketInfo>> {
+		let at = self.block_id(at)?;
+		self.client.runtime_api().bucket(&at, c2fc_id).map_err(client_error)
+	}
+
+	fn buckets_of_owner(&self, owner: AccountId, offset: Option<u64>, limit: Option<u64>, at: Option<H256>)
+	                    -> Result<Vec<BucketInfo>> {
+		let at = self.block_id(at)?;
+		self.client
+		    .runtime_api()
+		    .buckets_of_owner(&at, owner, offset.unwrap_or(0), limit.unwrap_or(DEFAULT_PAGE_SIZE))
+		    .map_err(client_error)
+	}
+
+	fn promises_of_owner(&self, owner: AccountId, offset: Option<u64>, limit: Option<u64>, at: Option<H256>)
+	                     -> Result<Vec<PromiseInfo>> {
+		let at = self.block_id(at)?;
+		self.client
+		    .runtime_api()
+		    .promises_of_owner(&at, owner, offset.unwrap_or(0), limit.unwrap_or(DEFAULT_PAGE_SIZE))
+		    .map_err(client_error)
+	}
+
+	fn free_promises(&self, filter: Option<PromiseFilter>, offset: Option<u64>, limit: Option<u64>, at: Option<H256>)
+	                 -> Result<Vec<PromiseInfo>> {
+		let at = self.block_id(at)?;
+		self.client
+		    .runtime_api()
+		    .free_promises(&at,
+		                   filter.unwrap_or_default(),
+		                   offset.unwrap_or(0),
+		                   limit.unwrap_or(DEFAULT_PAGE_SIZE))
+		    .map_err(client_error)
+	}
+
+	fn promise_schedule(&self, promise_id: Hash, at: Option<H256>) -> Result<Option<PromiseSchedule>> {
+		let at = self.block_id(at)?;
+		self.client.runtime_api().promise_schedule(&at, promise_id).map_err(client_error)
+	}
+
+	fn market_listings(&self, offset: Option<u64>, limit: Option<u64>, at: Option<H256>) -> Result<Vec<BucketInfo>> {
+		let at = self.block_id(at)?;
+		self.client
+		    .runtime_api()
+		    .buckets_for_sale(&at, offset.unwrap_or(0), limit.unwrap_or(DEFAULT_PAGE_SIZE))
+		    .map_err(client_error)
+	}
+}
+
+/// C2FC methods registered on the RPC handler of the service,
+/// so they are served over HTTP and WS with the standard methods.
+pub fn extensions<B, E, RA>(client: Arc<Client<B, E, Block, RA>>) -> IoHandler<Metadata>
+	where B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	      E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	      RA: Send + Sync + 'static,
+	      Client<B, E, Block, RA>: ProvideRuntimeApi,
+	      <Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: C2fcRuntimeApi<Block>
+{
+	let mut io = IoHandler::default();
+	io.extend_with(C2fc::new(client).to_delegate());
+	io
+}
@@ -105,6 +105,9 @@ construct_service_factory! {
 				}
 			},
 
+		RpcExtensions = jsonrpc_core::IoHandler<substrate_rpc::Metadata>
+			{ |client, _pool| crate::rpc::extensions(client) },
+
 		LightImportQueue = AuraImportQueue<Self::Block>
 			{ |config: &mut FactoryFullConfiguration<Self>, client: Arc<LightClient<Self>>| {
 					import_queue::<_, _, _, Pair>(